@@ -105,9 +105,11 @@ fn main() -> Result<()> {
     // Turn 'pins.toml' -> 'tmp/pins_snippet.in'
     {
         #[cfg(feature="vl53l8cx")]
-        const SENSOR_ID: &str = "vl53l8";   // without "cx"
-        #[cfg(feature="vl53l5cx")]
+        const SENSOR_ID: &str = "vl53l8";   // without "cx"; also for mixed L5CX/L8CX builds
+        #[cfg(all(feature="vl53l5cx", not(feature="vl53l8cx")))]
         const SENSOR_ID: &str = "vl53l5cx";
+        #[cfg(feature="vl53l7cx")]
+        const SENSOR_ID: &str = "vl53l7cx";
 
         let toml = include_str!("../pins.toml");
         let snippet: String = process_pins(toml, &board_id, SENSOR_ID)?;
@@ -9,9 +9,6 @@ use defmt::{info, debug, error, warn};
 use defmt_rtt as _;
 
 use esp_backtrace as _;     // needed for the panic handler to actually kick in
-use embassy_time as _;      // show it used in Cargo.toml
-
-use core::cell::RefCell;
 
 use embassy_executor::Spawner;
 
@@ -21,14 +18,15 @@ use embassy_sync::{
     channel::{Channel, DynamicReceiver, DynamicSender},
     signal::Signal,
 };
+use embassy_time::{Instant, Duration};
 
 use esp_hal::{
     delay::Delay,
     gpio::{AnyPin, Input, InputConfig, Level, Output, OutputConfig},
     i2c::master::{Config as I2cConfig, I2c},
-    time::{Instant, Duration, Rate},
+    time::Rate,
     timer::timg::TimerGroup,
-    Blocking
+    Async
 };
 
 use semihosting;
@@ -38,10 +36,14 @@ extern crate vl_api;
 use vl_api::{
     units::*,
     DEFAULT_I2C_ADDR,
+    FlockError,
+    FlockInt,
     FlockResults,
     I2cAddr,
+    I2cSpeed,
     Mode::*,
     RangingConfig,
+    SharedBus,
     TargetOrder::*,
     VL53,
     VLsExt as _,
@@ -54,7 +56,7 @@ include!("fake_alloc.in");
 const RESO: usize = 4;
 type FRes = FlockResults<RESO>;
 
-static I2C_SC: StaticCell<RefCell<I2c<'static, Blocking>>> = StaticCell::new();
+static I2C_SC: StaticCell<SharedBus<I2c<'static, Async>>> = StaticCell::new();
 
 #[allow(non_upper_case_globals)]
 const I2C_SPEED: Rate = Rate::from_khz(1000);        // max 1000
@@ -96,18 +98,19 @@ async fn main(spawner: Spawner) {
     let INT = Input::new(INT, InputConfig::default());  // no pull
 
     #[allow(non_snake_case)]
-    let LPn = LPn.map(|pin| { Output::new(pin, Level::Low, OutputConfig::default()) });
+    let mut LPn = LPn.map(|pin| { Output::new(pin, Level::Low, OutputConfig::default()) });
 
     let timg0 = TimerGroup::new(peripherals.TIMG0);
     esp_hal_embassy::init(timg0.timer0);
 
-    let i2c_shared: &'static RefCell<I2c<Blocking>> = {
+    let i2c_shared: &'static SharedBus<I2c<Async>> = {
         let i2c_bus = I2c::new(peripherals.I2C0, I2cConfig::default().with_frequency(I2C_SPEED))
             .unwrap()
             .with_sda(SDA)
-            .with_scl(SCL);
+            .with_scl(SCL)
+            .into_async();     // blocking use (ULD C code) is still available
 
-        let tmp = RefCell::new(i2c_bus);
+        let tmp = SharedBus::new(i2c_bus, I2cSpeed::FastPlus);     // matches 'I2C_SPEED'
         I2C_SC.init(tmp)
     };
 
@@ -119,9 +122,10 @@ async fn main(spawner: Spawner) {
         info!("Targets powered off and on again.");
     }
 
-    let vls = VL53::new_flock(LPn, i2c_shared,
+    let vls = VL53::new_flock(&mut LPn, i2c_shared, Delay::new(),
                 |i| I2cAddr::from_7bit(DEFAULT_I2C_ADDR.as_7bit() + (i as u8))
-    ).unwrap();
+    ) .await
+        .unwrap();
 
     info!("Init succeeded");
 
@@ -137,7 +141,7 @@ async fn main(spawner: Spawner) {
     static CHANNEL: Channel<CriticalSectionRawMutex, FRes, 2 /*max receivers*/> = Channel::new();
     let (snd,rcv) = (CHANNEL.dyn_sender(), CHANNEL.dyn_receiver());
 
-    spawner.spawn(ranging(vls, INT, snd)).unwrap();
+    spawner.spawn(ranging(vls, LPn, INT, snd)).unwrap();
 
     spawner.spawn(print_results(rcv)).unwrap();
 
@@ -171,7 +175,7 @@ async fn main(spawner: Spawner) {
 //
 #[embassy_executor::task]
 #[allow(non_snake_case)]
-async fn ranging(/*move*/ vls: [VL53;BOARDS_N], pin_INT: Input<'static>, snd: DynamicSender<'static, FRes>) {
+async fn ranging(/*move*/ vls: [VL53<I2c<'static, Async>>;BOARDS_N], mut LPns: [Output<'static>;BOARDS_N], pin_INT: Input<'static>, snd: DynamicSender<'static, FRes>) {
 
     debug!("Launched: ranging");
 
@@ -179,15 +183,25 @@ async fn ranging(/*move*/ vls: [VL53;BOARDS_N], pin_INT: Input<'static>, snd: Dy
         .with_mode(AUTONOMOUS(5.ms(), HzU8(10)))
         .with_target_order(CLOSEST);
 
-    let mut ring = vls.start_ranging(&c, pin_INT).unwrap();
+    let mut ring = vls.start_ranging(&c, FlockInt::Shared(pin_INT)) .await
+        .unwrap();
 
     let mut seen = [false;BOARDS_N];
 
     for _round in 0..10 {
         let mut _t = Timings::new();
 
-        let t: FlockResults<4> = ring.get_data() .await
-            .unwrap();
+        let t: FlockResults<4> = match ring.get_data() .await {
+            Ok(t) => t,
+            Err(FlockError::BoardLost(i)) => {
+                warn!("Board #{} lost; re-initializing it", i);
+                ring.reinit_board(i, &mut LPns, Delay::new()) .await
+                    .unwrap();
+                seen[i] = false;
+                continue;
+            },
+            Err(e) => panic!("{}", e),
+        };
         _t.results();
 
         if !seen[t.board_index] {
@@ -242,7 +256,7 @@ struct Timings {
 
 impl Timings {
     fn new() -> Self {
-        let dummy: Instant = Instant::EPOCH;
+        let dummy: Instant = Instant::MIN;
         Self{ t0: Instant::now(), t1: dummy, t2: dummy }
     }
 
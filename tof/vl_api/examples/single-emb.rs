@@ -11,21 +11,19 @@ use defmt::{info, debug, error, warn};
 use defmt_rtt as _;
 
 use esp_backtrace as _;     // needed for the panic handler to actually kick in
-use embassy_time as _;      // show it used in Cargo.toml
-
-use core::cell::RefCell;
 
 use embassy_executor::Spawner;
 
 use embassy_sync::signal::Signal;
+use embassy_time::{Instant, Duration};
 
 use esp_hal::{
     delay::Delay,
     gpio::{AnyPin, Input, InputConfig, Level, Output, OutputConfig},
     i2c::master::{Config as I2cConfig, I2c},
-    time::{Instant, Duration, Rate},
+    time::Rate,
     timer::timg::TimerGroup,
-    Blocking
+    Async
 };
 
 use semihosting;
@@ -35,8 +33,10 @@ extern crate vl_api;
 use vl_api::{
     units::*,
     DEFAULT_I2C_ADDR,
+    I2cSpeed,
     Mode::*,
     RangingConfig,
+    SharedBus,
     SoloResults,
     TargetOrder::*,
     VL53,
@@ -46,7 +46,7 @@ include!("../tmp/pins_snippet.in");  // pins!
 
 include!("fake_alloc.in");
 
-static I2C_SC: StaticCell<RefCell<I2c<'static, Blocking>>> = StaticCell::new();
+static I2C_SC: StaticCell<SharedBus<I2c<'static, Async>>> = StaticCell::new();
 
 #[allow(non_upper_case_globals)]
 const I2C_SPEED: Rate = Rate::from_khz(400);        // max 1000
@@ -89,16 +89,17 @@ async fn main(spawner: Spawner) {
     let timg0 = TimerGroup::new(peripherals.TIMG0);
     esp_hal_embassy::init(timg0.timer0);
 
-    let i2c_shared: &'static RefCell<I2c<Blocking>> = {
+    let i2c_shared: &'static SharedBus<I2c<Async>> = {
         let x = I2c::new(peripherals.I2C0, I2cConfig::default()
             .with_frequency(I2C_SPEED)
         ).unwrap();
 
         let i2c_bus = x
             .with_sda(SDA)
-            .with_scl(SCL);
+            .with_scl(SCL)
+            .into_async();     // blocking use (ULD C code) is still available
 
-        let tmp = RefCell::new(i2c_bus);
+        let tmp = SharedBus::new(i2c_bus, I2cSpeed::Fast);     // matches 'I2C_SPEED'
         I2C_SC.init(tmp)
     };
 
@@ -113,7 +114,7 @@ async fn main(spawner: Spawner) {
     // Enable one of the wired boards. Others remain low.
     LPn[0].set_high();
 
-    let vl = VL53::new_and_setup(&i2c_shared, &DEFAULT_I2C_ADDR)
+    let vl = VL53::new_and_setup(i2c_shared, &DEFAULT_I2C_ADDR, Delay::new()) .await
         .unwrap();
 
     info!("Init succeeded");
@@ -130,12 +131,12 @@ async fn main(spawner: Spawner) {
 
 #[embassy_executor::task]
 #[allow(non_snake_case)]
-async fn ranging(/*move*/ vl: VL53, pinINT: Input<'static>) {
+async fn ranging(/*move*/ vl: VL53<I2c<'static, Async>>, pinINT: Input<'static>) {
     let c = RangingConfig::<4>::default()
         .with_mode(AUTONOMOUS(5.ms(),HzU8(10)))  // 10.Hz() with 'fugit::Rate'
         .with_target_order(CLOSEST);
 
-    let mut ring = vl.start_ranging(&c, pinINT)
+    let mut ring = vl.start_ranging(&c, pinINT) .await
         .expect("ranging to start");
 
     let t0 = Instant::now();
@@ -185,7 +186,7 @@ struct Timings {
 
 impl Timings {
     fn new() -> Self {
-        let dummy = Instant::EPOCH;
+        let dummy = Instant::MIN;
         Self{ t0: dummy, t1: dummy, t2: dummy }
     }
 
@@ -0,0 +1,144 @@
+/*
+* The I2C bus, shared by the sensors (and possibly other devices). For L8 boards on SPI, the SPI
+* bus and the boards' chip selects (see 'spi.rs'); the tBUF part then doesn't apply.
+*
+* Besides the bus itself, we keep track of when the last transaction ended. The I2C spec (and
+* the VL53 datasheets, 'tBUF') require a bus free time between a STOP and the next START. Instead
+* of sleeping a fixed time after each transaction, we wait only what's remaining of that gap when
+* the next one begins. Often, nothing.
+*
+* The bus sits behind an 'embassy-sync' 'Mutex'. Other drivers (IMU, fuel gauge, ...) can share it,
+* e.g. with 'embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice::new(bus.mutex())'.
+*
+* The ULD C code is blocking; it reaches the bus via the 'Platform' ('Pl'), in the middle of a C
+* call. For this, we first lock the bus (async), then enter the C code. While so locked, 'Pl'
+* gets to the bus via 'held'. No 'await' happens until the lock is released.
+*/
+use core::cell::Cell;
+use core::ptr;
+
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    mutex::{Mutex, MutexGuard},
+};
+use embassy_time::{Duration, Instant};
+
+pub type BusMutex<I2C> = Mutex<CriticalSectionRawMutex, I2C>;
+
+/*
+* I2C bus speed; decides the bus free time.
+*
+* Values from the I2C spec (UM10204 Rev. 7, table 10); VL53L5CX datasheet has the same for 1MHz.
+*/
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum I2cSpeed {
+    Standard,       // 100kHz: 4.7us
+    Fast,           // 400kHz: 1.3us
+    FastPlus,       // 1MHz: 0.5us
+    Custom{ tbuf_ns: u32 },
+}
+
+impl I2cSpeed {
+    fn tbuf_ns(&self) -> u32 {
+        match self {
+            Self::Standard => 4700,
+            Self::Fast => 1300,
+            Self::FastPlus => 500,
+            Self::Custom{ tbuf_ns } => *tbuf_ns,
+        }
+    }
+}
+
+pub struct SharedBus<I2C> {
+    i2c: BusMutex<I2C>,
+    held: Cell<*mut I2C>,       // non-null while locked by 'lock()' (for the blocking ULD calls)
+    tbuf: Duration,
+    last_end: Cell<Instant>,
+}
+
+impl<I2C> SharedBus<I2C> {
+    pub fn new(i2c: I2C, speed: I2cSpeed) -> Self {
+        Self::with_tbuf(i2c, Duration::from_nanos(speed.tbuf_ns() as _))    // rounds up to the next tick
+    }
+
+    pub(crate) fn with_tbuf(i2c: I2C, tbuf: Duration) -> Self {
+        Self{
+            i2c: Mutex::new(i2c),
+            held: Cell::new(ptr::null_mut()),
+            tbuf,
+            last_end: Cell::new(Instant::MIN),
+        }
+    }
+
+    /*
+    * For sharing the bus with other drivers.
+    *
+    * Note: Transactions made this way don't update the bus free time tracking. The I2C peripheral
+    *       is expected to keep tBUF between its own transactions (most do).
+    */
+    pub fn mutex(&self) -> &BusMutex<I2C> {
+        &self.i2c
+    }
+
+    /*
+    * Lock the bus for blocking ULD calls. Do not 'await' while holding the returned value.
+    */
+    pub(crate) async fn lock(&self) -> BusLock<'_, I2C> {
+        let mut guard = self.i2c.lock() .await;
+        self.held.set(&mut *guard as *mut I2C);
+        BusLock{ bus: self, _guard: guard }
+    }
+
+    /*
+    * For the async readout ('APl'); doesn't expose the bus to the blocking side.
+    */
+    pub(crate) async fn lock_async(&self) -> MutexGuard<'_, CriticalSectionRawMutex, I2C> {
+        self.i2c.lock() .await
+    }
+
+    /*
+    * Access the bus from blocking code (within the ULD C calls).
+    *
+    * Normally, the bus is locked by 'lock()'. If not (e.g. 'State_Ranging' stopping in its 'Drop'),
+    * we try to lock it here; 'None' if someone else has it.
+    */
+    pub(crate) fn with_held<R>(&self, f: impl FnOnce(&mut I2C) -> R) -> Option<R> {
+        let p = self.held.get();
+        if !p.is_null() {
+            // SAFETY: 'held' is non-null only while a 'BusLock' exists; it owns the 'MutexGuard',
+            //      and clears 'held' before releasing it. So 'p' points to the locked 'I2C'.
+            //      The guard itself is never dereferenced, and 'f' (a single transfer) doesn't
+            //      call back here, so the '&mut' is the only reference to the 'I2C' while it
+            //      lives. Other tasks can't get in between: 'SharedBus' is '!Sync' ('Cell'), and
+            //      no 'await' happens while a 'BusLock' is held.
+            Some(f(unsafe { &mut *p }))
+        } else {
+            let mut guard = self.i2c.try_lock().ok()?;
+            Some(f(&mut guard))
+        }
+    }
+
+    /*
+    * How long to wait before starting a transaction; 'Duration::MIN' if none.
+    */
+    pub(crate) fn remaining_gap(&self) -> Duration {
+        let since = Instant::now().saturating_duration_since(self.last_end.get());
+        self.tbuf.checked_sub(since).unwrap_or(Duration::MIN)
+    }
+
+    pub(crate) fn mark_end(&self) {
+        self.last_end.set(Instant::now());
+    }
+}
+
+pub(crate) struct BusLock<'a, I2C> {
+    bus: &'a SharedBus<I2C>,
+    _guard: MutexGuard<'a, CriticalSectionRawMutex, I2C>,
+}
+
+impl<I2C> Drop for BusLock<'_, I2C> {
+    fn drop(&mut self) {
+        self.bus.held.set(ptr::null_mut());     // before the guard gets released
+    }
+}
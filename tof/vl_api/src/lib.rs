@@ -7,31 +7,62 @@ mod ranging;
 #[cfg(feature = "flock")]
 mod ranging_flock;
 
+mod bus;
+mod polling;
+#[cfg(feature = "vl53l8cx")]
+mod spi;
+#[cfg(feature = "flock_synced")]
+mod sync_gen;
+mod transport;
 mod uld_platform;
 mod vl53;
 
 #[cfg(feature = "single")]
-pub use ranging::{SoloResults, Ranging};
+pub use ranging::{RangingError, SoloResults, Ranging, Recovering};
 
 #[cfg(feature = "flock")]
 pub use {
-    ranging_flock::{FlockResults, RangingFlock},
+    ranging_flock::{FlockError, FlockInt, FlockResults, FrameSet, RangingFlock},
     vl53::VLsExt      // tbd. how to provide such methods properly?  Compare with 'fugit'.
 };
 
 pub use vl53::{
     VL53,
 };
+#[cfg(any(feature = "single", feature = "flock"))]
+pub use vl53::StartError;
+#[cfg(feature = "flock")]
+pub use vl53::FlockStartError;
+pub use bus::{BusMutex, I2cSpeed, SharedBus};
+#[cfg(feature = "vl53l8cx")]
+pub use spi::SpiCs;
+pub use transport::Transport;
+#[cfg(any(feature = "single", feature = "flock"))]
+pub use polling::NoInt;
+#[cfg(feature = "flock_synced")]
+pub use sync_gen::{SyncClock, SyncError, SyncGen};
+
+#[cfg(feature = "detection_thresholds")]
+pub use vl_uld::{Threshold, ThresholdCheck, ThresholdConfig, ThresholdMeas};
+#[cfg(feature = "xtalk")]
+pub use vl_uld::XtalkData;
 
 // Elements we pass through from the ULD level. Careful here: ideally all API is under our direct control!
 pub use vl_uld::{
     API_REVISION as ULD_VERSION,
+    ConfigError,
     DEFAULT_I2C_ADDR,
+    Error as UldError,
     I2cAddr,
     Mode,
+    PlatformError,
     RangingConfig,
     Result as UldResult,
     ResultsData,    // leaked (intentionally) via '{Flock|Solo}Results'
     TargetOrder,
     units,
+    Variant,
 };
+// Mixed L5CX/L8CX builds: see '.variant().fov_deg()' of each sensor.
+#[cfg(not(all(feature = "vl53l5cx", feature = "vl53l8cx")))]
+pub use vl_uld::{FOV_DEG, FOV_DIAGONAL_DEG};
@@ -0,0 +1,55 @@
+/*
+* Ranging without a wired 'INT' pin: poll the sensor(s) with 'is_ready()', instead.
+*
+* The API stays the same as with 'INT'. 'NoInt' takes the place of the pin type; it is never
+* waited on.
+*/
+#![cfg(any(feature = "single", feature = "flock"))]
+
+use core::convert::Infallible;
+
+use embassy_time::Duration;
+use embedded_hal::digital::{ErrorType, InputPin};
+use embedded_hal_async::digital::Wait;
+
+use vl_uld::RangingConfig;
+
+/*
+* Placeholder for the 'INT' pin type, when polling.
+*/
+pub struct NoInt;
+
+impl ErrorType for NoInt {
+    type Error = Infallible;
+}
+
+impl InputPin for NoInt {
+    fn is_high(&mut self) -> Result<bool, Infallible> { Ok(true) }     // the idle level of 'INT'
+    fn is_low(&mut self) -> Result<bool, Infallible> { Ok(false) }
+}
+
+impl Wait for NoInt {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> { Ok(()) }
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> { core::future::pending().await }
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> { core::future::pending().await }
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> { core::future::pending().await }
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> { core::future::pending().await }
+}
+
+/*
+* One ranging cycle. For 'CONTINUOUS', we don't set the frequency; the ULD default is 1Hz.
+*/
+pub(crate) fn frame_period<const DIM: usize>(cfg: &RangingConfig<DIM>) -> Duration {
+    Duration::from_hz(cfg.frequency().map_or(1, |hz| hz.0.max(1)) as u64)
+}
+
+/*
+* How often to ask the sensor(s) about new data. Each poll is a short I2C read (per board).
+*
+* A quarter of the ranging period keeps the time stamps within that much of the actual scan.
+*
+* tbd. Make configurable, if needed.
+*/
+pub(crate) fn poll_interval<const DIM: usize>(cfg: &RangingConfig<DIM>) -> Duration {
+    frame_period(cfg) / 4
+}
@@ -4,14 +4,19 @@
 #![cfg(feature = "single")]
 
 #[cfg(feature = "defmt")]
-use defmt::{trace};
+use defmt::{trace, warn};
 
-use esp_hal::{
-    gpio::Input,
-    time::Instant
+use core::fmt::{Display, Formatter};
+
+use embassy_time::{with_timeout, Duration, Instant, Timer};
+use embedded_hal::{
+    delay::DelayNs,
+    digital::OutputPin,
 };
+use embedded_hal_async::digital::Wait;
 
 use vl_uld::{
+    Error as UldError,
     RangingConfig,
     Result,
     ResultsData,
@@ -20,6 +25,10 @@ use vl_uld::{
 };
 
 use crate::{
+    polling::frame_period,
+    transport::Transport,
+    uld_platform::APl,
+    vl53::StartError,
     VL53,
 };
 
@@ -30,23 +39,90 @@ pub struct SoloResults<const DIM: usize>{
     pub time_stamp: Instant,
 }
 
+/*
+* Reasons '.get_data()' can fail, on top of the ULD ones.
+*
+* Both 'Timeout' and 'SpuriousInt' suggest the sensor has stalled; see '.recover()'.
+*/
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RangingError {
+    Uld(UldError),
+    Timeout(Duration),      // no data within the time (see '.with_timeout()')
+    SpuriousInt,            // 'INT' edge seen, but the sensor has no data
+}
+
+impl Display for RangingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Uld(e) => write!(f, "{}", e),
+            Self::Timeout(dt) => write!(f, "no data within {}ms", dt.as_millis()),
+            Self::SpuriousInt => write!(f, "INT edge seen, but the sensor has no data"),
+        }
+    }
+}
+
+impl From<UldError> for RangingError {
+    fn from(e: UldError) -> Self { Self::Uld(e) }
+}
+
 /*
 * Ranging for a single board.
 */
 #[cfg(feature = "single")]
-pub struct Ranging<const DIM: usize> {    // DIM: 4|8
+pub struct Ranging<const DIM: usize, INT: Wait, B: Transport + 'static> {    // DIM: 4|8
     uld: State_Ranging<DIM>,
-    pinINT: Input<'static>
+    apl: APl<'static, B>,
+    pinINT: INT,
+    poll: Option<Duration>,     // 'Some' if polling (no 'INT' wired; 'INT' is 'NoInt')
+    cfg: RangingConfig<DIM>,    // for restarting, in '.recover()'
+    timeout: Option<Duration>,
 }
 
 #[cfg(feature = "single")]
-impl<const DIM: usize> Ranging<DIM> {
-    pub(crate) fn start(vl: VL53, cfg: &RangingConfig<DIM>, pinINT: Input<'static>) -> Result<Ranging<DIM>> {
-        let uld = vl.into_uld().start_ranging(cfg)?;
-        Ok(Self{ uld, pinINT })
+impl<const DIM: usize, INT: Wait, B: Transport + 'static> Ranging<DIM,INT,B> {
+    // On failure, the 'VL53' and the pin are given back; the sensor is still idle.
+    //
+    pub(crate) async fn start(vl: VL53<B>, cfg: &RangingConfig<DIM>, pinINT: INT, poll: Option<Duration>) -> core::result::Result<Self, StartError<VL53<B>,INT>> {
+        let (uld, apl) = vl.into_parts();
+        let r = {
+            let _lock = apl.bus().lock() .await;
+            uld.start_ranging(cfg)
+        };
+        match r {
+            Ok(uld) => Ok(Self{ uld, apl, pinINT, poll, cfg: cfg.clone(), timeout: default_timeout(cfg) }),
+            Err(e) => Err(StartError{ error: e.error, vl: VL53::recreate(e.vl, apl), int: pinINT }),
+        }
+    }
+
+    /*
+    * How long '.get_data()' waits for the next results, before giving 'RangingError::Timeout'.
+    * 'None' waits forever.
+    *
+    * The default is two ranging periods. With detection thresholds in use, there's no default
+    * timeout (the sensor only reports when the criteria are met).
+    */
+    pub fn with_timeout(/*move*/ self, timeout: Option<Duration>) -> Self {
+        Self{ timeout, ..self }
     }
 
-    pub async fn get_data(&mut self) -> Result<SoloResults<DIM>> {
+    pub async fn get_data(&mut self) -> core::result::Result<SoloResults<DIM>, RangingError> {
+        self.get_data_within(self.timeout) .await
+    }
+
+    /*
+    * Wait until the detection thresholds (given in the 'RangingConfig') are met, and provide the
+    * results of that frame. 'timeout' of 'None' waits forever; '.with_timeout()' doesn't apply.
+    *
+    * If the config has no thresholds, the sensor reports on each frame; this then equals
+    * '.get_data()'.
+    */
+    #[cfg(feature = "detection_thresholds")]
+    pub async fn wait_for_threshold(&mut self, timeout: Option<Duration>) -> core::result::Result<SoloResults<DIM>, RangingError> {
+        self.get_data_within(timeout) .await
+    }
+
+    async fn get_data_within(&mut self, timeout: Option<Duration>) -> core::result::Result<SoloResults<DIM>, RangingError> {
         let now = Instant::now;
         let t0 = now();
 
@@ -64,22 +140,160 @@ impl<const DIM: usize> Ranging<DIM> {
         //
         // Since we time the results, 1st feels more.. better choice. For now, at least.
         //
-        self.pinINT.wait_for_falling_edge() .await;
+        // Note: With detection thresholds (in the 'RangingConfig'), INT is only raised when the
+        //      criteria are met; see '.wait_for_threshold()'.
+        //
+        // Note: When polling, results already available (since the last call) are provided.
+        //      The timeout only bounds the waits between the polls; a poll (I2C) is not cut.
+        //
+        if let Some(interval) = self.poll {
+            let deadline = timeout.map_or(Instant::MAX, |dt| t0 + dt);
+            let ts = match self.poll_until_ready(interval, deadline) .await? {
+                Some(ts) => ts,
+                None => return Err(RangingError::Timeout(timeout.unwrap())),    // only with a deadline
+            };
+
+            let (res, temp_degc) = self.uld.get_data_async(&mut self.apl) .await?;
+            return Ok( SoloResults{ res, temp_degc, time_stamp: ts } );
+        }
+
+        within(timeout, self.pinINT.wait_for_falling_edge()) .await?
+            .expect("INT pin to be waitable");
         let ts = now();     // nearest time after the (presumed) scan
 
         trace!("Received falling edge of INT, after {}", ts - t0);
 
-        match self.uld.is_ready()? {
-            true => (),
-            false => panic!("INT edge seen but sensor has no data"),
+        let ready = self.uld.is_ready_async(&mut self.apl) .await?;
+        if !ready {
+            warn!("INT edge seen but sensor has no data");
+            return Err(RangingError::SpuriousInt);
         }
 
-        let (res, temp_degc) = self.uld.get_data()?;
+        // The results block gets read async; other tasks keep running.
+        let (res, temp_degc) = self.uld.get_data_async(&mut self.apl) .await?;
         Ok( SoloResults{ res, temp_degc, time_stamp: ts } )
     }
 
-    pub fn stop(self) -> Result<VL53> {
+    /*
+    * Returns the time of seeing the data ready; 'None' if not ready by 'deadline' (polled once
+    * more, at it).
+    */
+    async fn poll_until_ready(&mut self, interval: Duration, deadline: Instant) -> Result<Option<Instant>> {
+        loop {
+            let ready = self.uld.is_ready_async(&mut self.apl) .await?;
+            let now = Instant::now();
+            if ready {
+                return Ok(Some(now));
+            } else if now >= deadline {
+                return Ok(None);
+            }
+            Timer::at((now + interval).min(deadline)) .await;
+        }
+    }
+
+    /*
+    * Stop the ranging; waits for the bus. Prefer this over dropping: the drop only tries the bus
+    * (it cannot wait), and if another task has it, the sensor keeps ranging (a warning is logged).
+    */
+    pub async fn stop(self) -> Result<VL53<B>> {
+        let _lock = self.apl.bus().lock() .await;
         let uld = self.uld.stop()?;
-        Ok(VL53::recreate(uld))
+        Ok(VL53::recreate(uld, self.apl))
+    }
+
+    /*
+    * Get a stalled sensor back to ranging: power cycle it, initialize it again (with the same I2C
+    * address, or chip select), and restart ranging with the same config and timeout.
+    *
+    * 'pwr' is the line cutting the board's power ('PWR_EN'); the board's 'LPn' also works, though
+    *       it may not reset the sensor as thoroughly. 'delay' is for the ULD C code, like in
+    *       'VL53::new_and_setup()'.
+    *
+    * If that fails, the rest of the session comes back as 'Recovering'; try again later with
+    * '.retry()'.
+    *
+    * Note: Settings made on the 'VL53' before ranging (cross-talk, VHV, charge pump) are not
+    *       restored; those are the sensor defaults, again.
+    * Note: Takes about as long as the initial setup (the firmware gets uploaded again).
+    */
+    pub async fn recover<P: OutputPin, D: DelayNs + Clone + 'static>(self, pwr: &mut P, delay: D) -> core::result::Result<Self, (RangingError, Recovering<DIM,INT,B>)> {
+        let Self{ uld, apl, pinINT, poll, cfg, timeout } = self;
+
+        uld.abandon();      // don't talk to the sensor; it's not answering
+
+        Recovering{ apl, pinINT, poll, cfg, timeout }.retry(pwr, delay) .await
+    }
+}
+
+/*
+* A 'Ranging' whose sensor didn't come back, in '.recover()'. Keeps what's needed for another
+* try: the bus and address (or chip select), the 'INT' pin, the config and timeout.
+*/
+#[cfg(feature = "single")]
+pub struct Recovering<const DIM: usize, INT: Wait, B: Transport + 'static> {
+    apl: APl<'static, B>,
+    pinINT: INT,
+    poll: Option<Duration>,
+    cfg: RangingConfig<DIM>,
+    timeout: Option<Duration>,
+}
+
+#[cfg(feature = "single")]
+impl<const DIM: usize, INT: Wait, B: Transport + 'static> Recovering<DIM,INT,B> {
+    /*
+    * Power cycle the sensor, and try getting it back to ranging; see 'Ranging::recover()'.
+    */
+    pub async fn retry<P: OutputPin, D: DelayNs + Clone + 'static>(self, pwr: &mut P, delay: D) -> core::result::Result<Ranging<DIM,INT,B>, (RangingError, Self)> {
+        let bus = self.apl.bus();
+        let target = self.apl.target().clone();
+
+        warn!("Power cycling the sensor");
+        pwr.set_low().expect("power line to be settable");
+        Timer::after_millis(10) .await;     // L5CX: 10ms based on UM2884 Rev. 6, Chapter 4.2
+        pwr.set_high().expect("power line to be settable");
+
+        let vl = match VL53::reinit(bus, &target, delay) .await {
+            Ok(vl) => vl,
+            Err(e) => return Err((e.into(), self)),
+        };
+
+        let (uld, apl) = vl.into_parts();
+        let r = {
+            let _lock = apl.bus().lock() .await;
+            uld.start_ranging(&self.cfg)
+        };
+        match r {
+            Ok(uld) => {
+                let Self{ pinINT, poll, cfg, timeout, .. } = self;
+                Ok( Ranging{ uld, apl, pinINT, poll, cfg, timeout } )
+            },
+            Err(e) => Err((e.error.into(), Self{ apl, ..self })),
+        }
+    }
+
+    /*
+    * Give up on the sensor; get the 'INT' pin back.
+    */
+    pub fn into_pin(self) -> INT {
+        self.pinINT
+    }
+}
+
+/*
+* Two frames' worth, unless the frames may not come regularly (detection thresholds).
+*/
+fn default_timeout<const DIM: usize>(cfg: &RangingConfig<DIM>) -> Option<Duration> {
+    #[cfg(feature = "detection_thresholds")]
+    if cfg.has_thresholds() {
+        return None;
+    }
+    Some(frame_period(cfg) * 2)
+}
+
+async fn within<T>(timeout: Option<Duration>, f: impl Future<Output = T>) -> core::result::Result<T, RangingError> {
+    match timeout {
+        None => Ok(f.await),
+        Some(dt) => with_timeout(dt, f) .await
+            .map_err(|_| RangingError::Timeout(dt)),
     }
 }
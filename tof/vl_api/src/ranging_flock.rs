@@ -4,15 +4,22 @@
 #![cfg(feature = "flock")]
 
 #[cfg(feature = "defmt")]
-use defmt::{debug,trace};
+use defmt::{debug,warn};
 
-use esp_hal::{
-    gpio::Input,
-    time::Instant
+use core::fmt::{Display, Formatter};
+use core::mem;
+
+use embassy_futures::select::{select, select_array, Either};
+use embassy_time::{Duration, Instant, Timer};
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
 };
+use embedded_hal_async::digital::Wait;
 
 use vl_uld::{
     units::TempC,
+    Error as UldError,
     RangingConfig,
     Result,
     ResultsData,
@@ -22,6 +29,11 @@ use vl_uld::{
 use arrayvec::ArrayVec;
 
 use crate::{
+    bus::SharedBus,
+    polling::frame_period,
+    transport::Transport,
+    uld_platform::APl,
+    vl53::{FlockStartError, StartError},
     VL53
 };
 
@@ -33,41 +45,361 @@ pub struct FlockResults<const DIM: usize>{
     pub time_stamp: Instant,
 }
 
+/*
+* One ranging cycle's worth of results, by board index. 'None' for boards that missed the cycle
+* (their results didn't arrive within the cycle's time window).
+*/
+#[derive(Clone, Debug)]
+pub struct FrameSet<const N: usize, const DIM: usize> {
+    pub frames: [Option<FlockResults<DIM>>;N],
+}
+
+impl<const N: usize, const DIM: usize> FrameSet<N,DIM> {
+    pub fn missed(&self, board_index: usize) -> bool {
+        self.frames[board_index].is_none()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.frames.iter().all(Option::is_some)
+    }
+}
+
+/*
+* Reasons '.get_data()' (and '.get_frame_set()') can fail, on top of the ULD ones.
+*/
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FlockError {
+    Uld(UldError),
+    BoardLost(usize),       // board stopped answering (e.g. browned out); see '.reinit_board()'
+}
+
+impl Display for FlockError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Uld(e) => write!(f, "{}", e),
+            Self::BoardLost(i) => write!(f, "board #{} not answering", i),
+        }
+    }
+}
+
+impl From<UldError> for FlockError {
+    fn from(e: UldError) -> Self { Self::Uld(e) }
+}
+
+/*
+* How the boards' 'INT' lines are wired.
+*
+* 'Shared': one line for all. Each edge means polling all boards (over the bus) to see, which have data.
+* 'PerBoard': one line per board, in board order. The edge tells which board has data; no polling.
+* 'Polled': no line; all boards get polled at the interval (see 'VLsExt::start_ranging_polled').
+*/
+pub enum FlockInt<INT, const N: usize> {
+    Shared(INT),
+    PerBoard([INT;N]),
+    Polled(Duration),
+}
+
 /*
 * State for scanning multiple VL53L5CX boards.
 *
 * Note: A generator would be ideal for this (could keep the state within it).
 */
-pub struct RangingFlock<const N: usize, const DIM: usize> {
+pub struct RangingFlock<const N: usize, const DIM: usize, INT: Wait + InputPin, B: Transport + 'static> {
     ulds: [State_Ranging<DIM>;N],
-    pinINT: Input<'static>,
-    pending: ArrayVec<FlockResults<DIM>,N>    // tbd. pick suitable capacity once we know the behaviour
+    apls: [APl<'static, B>;N],
+    ints: FlockInt<INT,N>,
+    pending: ArrayVec<FlockResults<DIM>,N>,   // tbd. pick suitable capacity once we know the behaviour
+    period: Duration,                       // one ranging cycle (of the slowest board)
+    cfgs: [RangingConfig<DIM>;N],           // for restarting a board, in '.reinit_board()'
+    lost: [bool;N],                         // boards left out of the scanning, until re-initialized
+    last_seen: [Instant;N],                 // last results (or start), per board
 }
 
-impl<const N: usize, const DIM: usize> RangingFlock<N,DIM> {
+impl<const N: usize, const DIM: usize, INT: Wait + InputPin, B: Transport + 'static> RangingFlock<N,DIM,INT,B> {
 
-    pub(crate) fn start(vls: [VL53;N], cfg: &RangingConfig<DIM>, pinINT: Input<'static>) -> Result<Self> {
+    /*
+    * Each board gets its own config (they can be the same). They share the resolution ('DIM').
+    *
+    * All configs are checked before any board is started. If starting a board still fails (I2C),
+    * the boards started before it are stopped; none is left ranging. The 'VL53's and 'ints' are
+    * given back (see 'FlockStartError').
+    *
+    * tbd. Mixed resolutions would need the results to be an enum (or per-board types).
+    */
+    pub(crate) async fn start(vls: [VL53<B>;N], cfgs: &[RangingConfig<DIM>;N], ints: FlockInt<INT,N>) -> core::result::Result<Self, FlockStartError<N,INT,B>> {
+        for cfg in cfgs {
+            if let Err(e) = cfg.check() {
+                return Err(StartError{ error: e.into(), vl: Some(vls), int: ints });
+            }
+        }
 
-        // Turn the ULD level handles into "ranging" state, and start tracking the 'pinINT'.
+        // Turn the ULD level handles into "ranging" state, and start tracking the 'INT' pin(s).
+        //
+        // Note: All boards share the same bus; lock it once, for starting them all.
+        //
+        let bus = vls[0].bus();
+        let _lock = bus.lock() .await;
+
+        let period = cfgs.iter().map(frame_period).max().unwrap_or_default();
+
+        let mut ulds = ArrayVec::<State_Ranging<DIM>,N>::new();
+        let mut apls = ArrayVec::<APl<'static, B>,N>::new();
+
+        let mut vls = vls.into_iter();
+
+        for cfg in cfgs {
+            let (uld, apl) = vls.next().unwrap().into_parts();
+            apls.push(apl);
+
+            match uld.start_ranging(cfg) {
+                Ok(x) => ulds.push(x),
+                Err(e) => {
+                    warn!("Starting board #{} failed; stopping the ones already started", ulds.len());
+
+                    let mut back = ArrayVec::<VL53<B>,N>::new();
+                    let mut all_back = true;
+                    let mut apls = apls.into_iter();
+
+                    for (i, x) in ulds.into_iter().enumerate() {
+                        let apl = apls.next().unwrap();
+                        match x.stop() {
+                            Ok(uld) => back.push(VL53::recreate(uld, apl)),
+                            Err(_e) => {
+                                warn!("Stopping board #{} failed: {}", i, _e);
+                                all_back = false;
+                            }
+                        }
+                    }
+                    back.push(VL53::recreate(e.vl, apls.next().unwrap()));
+                    back.extend(vls);
 
-        let ulds: [State_Ranging<DIM>;N] = array_try_map(vls, |x| x.into_uld().start_ranging(cfg))?;
+                    let vl = if all_back { back.into_inner().ok() } else { None };
+                    return Err(StartError{ error: e.error, vl, int: ints });
+                }
+            }
+        }
 
         Ok(Self{
-            ulds,
-            pinINT,
-            pending: ArrayVec::new()
+            ulds: ulds.into_inner().ok().unwrap(),
+            apls: apls.into_inner().ok().unwrap(),
+            ints,
+            pending: ArrayVec::new(),
+            period,
+            cfgs: cfgs.clone(),
+            lost: [false;N],
+            last_seen: [Instant::now();N],
         })
     }
 
+    /*
+    * Get a full set of results: one per board, for the same ranging cycle.
+    *
+    * The cycle's time window starts with the first result that arrives. It ends once all boards
+    * have reported, or when one ranging period has passed. Boards that didn't report within the
+    * window are marked missed ('None'). If a board reports twice, the newer stays.
+    *
+    * The window only limits waiting for the boards; a readout that has begun is completed.
+    *
+    * Note: Boards aren't synchronized to each other, unless the SYNC pin is used (L8 only;
+    *       'flock_synced' feature). Without it, a set may contain results up to one ranging
+    *       period apart.
+    * Note: With per-board configs, the window is the period of the slowest board. Faster boards
+    *       report their latest results within it.
+    */
+    pub async fn get_frame_set(&mut self) -> core::result::Result<FrameSet<N,DIM>, FlockError> {
+        let mut frames: [Option<FlockResults<DIM>>;N] = [const { None };N];
+
+        let first = self.get_data() .await?;
+        let deadline = first.time_stamp + self.period;
+        let i = first.board_index;
+        frames[i] = Some(first);
+
+        while !frames.iter().all(Option::is_some) {
+            match self.get_data_until(deadline) .await? {
+                Some(o) => {
+                    let i = o.board_index;
+                    if frames[i].as_ref().is_none_or(|x| x.time_stamp <= o.time_stamp) {
+                        frames[i] = Some(o);
+                    }
+                },
+                None => {
+                    debug!("Frame set window closed; missing {}", frames.iter().filter(|x| x.is_none()).count());
+                    break;
+                }
+            }
+        }
+
+        Ok( FrameSet{ frames } )
+    }
+
     /*
     * Get the next available results.
     *
     * By design, we provide just one result at a time. This is akin to streaming/generation, and
     *       makes it easier for the recipient, compared to getting 1..N results, at once.
     */
-    // tbd. Consider waiting for 'INT' first, and returning *all* the entries to the caller,
-    //      at once (as a slice?).
-    pub async fn get_data(&mut self) -> Result<FlockResults<DIM>> {
+    // Note: For getting all the boards' results at once, see '.get_frame_set()'.
+    //
+    // A board that stops answering (I2C) is reported once, as 'FlockError::BoardLost', and left
+    // out of the scanning until '.reinit_board()'. The other boards keep streaming.
+    //
+    pub async fn get_data(&mut self) -> core::result::Result<FlockResults<DIM>, FlockError> {
+        let o = self.get_data_until(Instant::MAX) .await?;
+        Ok( o.unwrap() )    // no deadline
+    }
+
+    /*
+    * Like '.get_data()', but 'None' if no results by the 'deadline'. Only the waiting (for 'INT',
+    * or the next poll) is limited; reading out the boards is not cut short.
+    */
+    async fn get_data_until(&mut self, deadline: Instant) -> core::result::Result<Option<FlockResults<DIM>>, FlockError> {
+        let o = match self.ints {
+            FlockInt::Shared(_) | FlockInt::Polled(_) => self.get_data_shared(deadline) .await,
+            FlockInt::PerBoard(_) => self.get_data_per_board(deadline) .await,
+        }?;
+        if let Some(o) = &o {
+            self.last_seen[o.board_index] = o.time_stamp;
+        }
+        Ok(o)
+    }
+
+    /*
+    * Boards that have been left out of the scanning (see '.get_data()').
+    */
+    pub fn is_lost(&self, board_index: usize) -> bool {
+        self.lost[board_index]
+    }
+
+    /*
+    * Time since the board last provided results (or since the start). Boards that don't run into
+    * I2C errors (e.g. per-board 'INT', and the board has reset) only show up this way. Same for
+    * boards on SPI (a reset board gives no bus errors).
+    */
+    pub fn silent_for(&self, board_index: usize) -> Duration {
+        self.last_seen[board_index].elapsed()
+    }
+
+    /*
+    * Re-initialize a board that's been lost (or is silent), and get it back into the ranging, with
+    * the same I2C address (or chip select) and config. The other boards stay ranging.
+    *
+    * 'LPns' are the same as given to 'VL53::new_flock()'. The board's 'LPn' is toggled, to start
+    * afresh; a board using the default I2C address is kept off the bus meanwhile (a reset board
+    * also answers at the default address). Over SPI, that doesn't apply.
+    *
+    * Note: The ULD initialization is blocking (the firmware upload takes a while). Other boards'
+    *       frames that complete in the mean time are not read out (only the latest is kept by
+    *       the sensors).
+    */
+    pub async fn reinit_board<LPN: OutputPin, D: DelayNs + Clone + 'static>(&mut self,
+        board_index: usize,
+        LPns: &mut [LPN;N],
+        delay: D
+    ) -> core::result::Result<(), FlockError> {
+        let i = board_index;
+        let bus = self.bus();
+        let target = self.apls[i].target().clone();
+
+        warn!("Re-initializing board #{}", i);
+
+        // Another board where this one answers after the reset (I2C: the default address).
+        let at_initial = B::initial(&target);
+        let other_at_default = (0..N).find(|&j| j != i && *self.apls[j].target() == at_initial);
+
+        if let Some(j) = other_at_default {
+            LPns[j].set_low().expect("LPn to be settable");
+        }
+        LPns[i].set_low().expect("LPn to be settable");
+        Timer::after_millis(10) .await;
+        LPns[i].set_high().expect("LPn to be settable");
+
+        let r = VL53::reinit(bus, &target, delay) .await;
+
+        if let Some(j) = other_at_default {
+            LPns[j].set_high().expect("LPn to be settable");
+        }
+
+        let (uld, apl) = r?.into_parts();
+        let uld = {
+            let _lock = bus.lock() .await;
+            uld.start_ranging(&self.cfgs[i])
+                .map_err(UldError::from)?
+        };
+
+        mem::replace(&mut self.ulds[i], uld).abandon();     // the old one: don't talk to it
+        self.apls[i] = apl;
+        self.pending.retain(|o| o.board_index != i);
+        self.lost[i] = false;
+        self.last_seen[i] = Instant::now();
+
+        debug!("Board #{} back in the flock", i);
+        Ok(())
+    }
+
+    /*
+    * Leave out a board whose I2C comms fail; report it (once) to the caller.
+    */
+    fn lost_if_gone<T>(&mut self, i: usize, r: Result<T>) -> core::result::Result<T, FlockError> {
+        match r {
+            Err(UldError::Platform(_e)) => {
+                warn!("Board #{} not answering ({}); leaving it out", i, _e);
+                self.lost[i] = true;
+                Err(FlockError::BoardLost(i))
+            },
+            r => Ok(r?)
+        }
+    }
+
+    /*
+    * With per-board 'INT' lines, wait for a falling edge on any of them, and read that board.
+    *
+    * 'select_array' only tells one of the lines. Boards whose lines are also low (edges at about
+    * the same time) are read right after, and their results queued in 'pending'. Other boards are
+    * not polled; they are left to their own edges.
+    *
+    * Note: Like with a single board, edges are only seen while we wait. If the caller is slow in
+    *       coming back here, a frame may be missed.
+    */
+    async fn get_data_per_board(&mut self, deadline: Instant) -> core::result::Result<Option<FlockResults<DIM>>, FlockError> {
+        loop {
+            if !self.pending.is_empty() {
+                return Ok( Some(self.pending.remove(0)) );
+            }
+
+            let FlockInt::PerBoard(pins) = &mut self.ints else { unreachable!() };
+
+            let edge = select(
+                select_array( pins.each_mut().map(|pin| pin.wait_for_falling_edge()) ),
+                Timer::at(deadline)
+            ) .await;
+            let (r, i) = match edge {
+                Either::First(x) => x,
+                Either::Second(()) => return Ok(None),
+            };
+            r.expect("INT pin to be waitable");
+            let time_stamp = Instant::now();
+
+            // Lines still low were pulled at about the same time.
+            let low: [bool;N] = core::array::from_fn(|j| pins[j].is_low().expect("INT pin to be readable"));
+
+            for j in (i..N).chain(0..i) {   // the woken one first
+                if self.lost[j] || !(j == i || low[j]) {
+                    continue;
+                }
+                debug!("New data from #{} (own INT)", j);
+
+                let r = self.ulds[j].get_data_async(&mut self.apls[j]) .await;
+                let (res,temp_degc) = self.lost_if_gone(j, r)?;
+                self.pending.push( FlockResults{ board_index: j, res, temp_degc, time_stamp } );
+            }
+        }
+    }
+
+    /*
+    * Shared 'INT' line, or polling (the same logic; just sleeping differently).
+    */
+    async fn get_data_shared(&mut self, deadline: Instant) -> core::result::Result<Option<FlockResults<DIM>>, FlockError> {
 
         // Time stamp the results as fast after knowing they exist, as possible.
 
@@ -84,22 +416,19 @@ impl<const N: usize, const DIM: usize> RangingFlock<N,DIM> {
         //        (they can be both delivered)
         //      - time stamps should be as close to actual measurement as possible!
 
-        // Trace if we see new data
-        //#[cfg(false)]
-        {
-            for (i,uld) in self.ulds.iter_mut().enumerate() {
-                if uld.is_ready()? {
-                    trace!("Data available on entry: {}", i);
-                }
-            }
-        }
-
         loop {
-            // Add new results to the 'self.pending'.
-            for (i,uld) in self.ulds.iter_mut().enumerate() /*.rev()*/ {
-                if uld.is_ready()? {
+            // Add new results to the 'self.pending'. Boards already there are left for the next
+            // round (keeps the queue at one per board).
+            for i in 0..N /*.rev()*/ {
+                if self.lost[i] || self.pending.iter().any(|o| o.board_index == i) {
+                    continue;
+                }
+                let r = self.ulds[i].is_ready_async(&mut self.apls[i]) .await;
+                let ready = self.lost_if_gone(i, r)?;
+                if ready {
                     let time_stamp = Instant::now();
-                    let (res,temp_degc) = uld.get_data()?;
+                    let r = self.ulds[i].get_data_async(&mut self.apls[i]) .await;
+                    let (res,temp_degc) = self.lost_if_gone(i, r)?;
                     let o = FlockResults{ board_index: i, res, temp_degc, time_stamp };
 
                     let n = self.pending.len();
@@ -114,9 +443,9 @@ impl<const N: usize, const DIM: usize> RangingFlock<N,DIM> {
                 }
             }
 
-            // Return already pending results, one at a time.
-            if let Some(tmp) = self.pending.pop() {
-                return Ok(tmp);
+            // Return already pending results, one at a time; oldest first.
+            if !self.pending.is_empty() {
+                return Ok( Some(self.pending.remove(0)) );
             }
 
             // No data; sleep until either edge
@@ -128,26 +457,55 @@ impl<const N: usize, const DIM: usize> RangingFlock<N,DIM> {
             //      hear of. Checking both edges ensures we get even those, with sub-ms delay.
             //
             assert!(self.pending.is_empty());
+
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+
+            if let FlockInt::Polled(interval) = self.ints {
+                Timer::at((Instant::now() + interval).min(deadline)) .await;
+                continue;
+            }
             {
-                debug!("Going to sleep (INT {}).", if self.pinINT.is_low() {"still low"} else {"high"});
+                debug!("Going to sleep (INT {}).", if self.int_is_low() {"still low"} else {"high"});
 
                 let t0 = Instant::now();
-                self.pinINT.wait_for_any_edge().await;
+                let FlockInt::Shared(pin) = &mut self.ints else { unreachable!() };
+                match select(pin.wait_for_any_edge(), Timer::at(deadline)) .await {
+                    Either::First(r) => r.expect("INT pin to be waitable"),
+                    Either::Second(()) => continue,     // one more poll, then 'None'
+                }
 
                 debug!("Woke up to INT edge (now {}; slept {}ms)",
-                    if self.pinINT.is_low() {"low"} else {"high"}, t0.elapsed().as_millis()
+                    if self.int_is_low() {"low"} else {"high"}, t0.elapsed().as_millis()
                 );
             }
         }
     }
 
-    pub fn stop(self) -> Result<([VL53;N], Input<'static>)> {
+    #[allow(dead_code)]     // only used for 'debug!' output
+    fn int_is_low(&mut self) -> bool {
+        let FlockInt::Shared(pin) = &mut self.ints else { unreachable!() };
+        pin.is_low().expect("INT pin to be readable")
+    }
+
+    fn bus(&self) -> &'static SharedBus<B> {
+        self.apls[0].bus()
+    }
+
+    /*
+    * Stop the ranging, on all boards. Prefer this over dropping; see 'Ranging::stop()'.
+    */
+    pub async fn stop(self) -> Result<([VL53<B>;N], FlockInt<INT,N>)> {
+        let _lock = self.bus().lock() .await;
+
+        let mut apls = self.apls.into_iter();
         let vls = array_try_map(self.ulds, |x| {
             let uld = x.stop()?;
-            Ok( VL53::recreate(uld) )
+            Ok( VL53::recreate(uld, apls.next().unwrap()) )
         })?;
 
-        Ok( (vls, self.pinINT) )
+        Ok( (vls, self.ints) )
     }
 }
 
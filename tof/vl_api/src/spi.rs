@@ -0,0 +1,147 @@
+/*
+* SPI transport, for the VL53L8CX (the L5CX is I2C only).
+*
+* One SPI bus, one chip select ('NCS') line per board. A board is reached by its index (its chip
+* select, in 'cs' order); there are no I2C addresses to hand out, so setting up a flock doesn't
+* need the 'LPn' lines toggled one by one ('VL53::new_flock_spi()').
+*
+* The boards need to be strapped for SPI ('SPI_I2C_N' high; see the SATEL-VL53L8 schematics).
+* The L8 datasheet gives SPI mode 3, up to 3MHz. Configure the SPI peripheral for those.
+*
+* Framing (as in the vendor's SPI platform code):
+*   - two bytes of register index, MSB first; the top bit set for a write, clear for a read
+*   - data, for as many bytes as needed (the ULD reads up to ~1.4kB in one go)
+*
+* Note: Over SPI, a board that has reset (or lost power) doesn't cause bus errors; reads just
+*       return garbage (or zeros). Such a board is not flagged by 'RangingFlock' as lost; use
+*       '.silent_for()' to detect it.
+*/
+#![cfg(feature = "vl53l8cx")]
+
+use embedded_hal::{
+    digital::OutputPin,
+    spi::SpiBus,
+};
+use embedded_hal_async::spi::SpiBus as AsyncSpiBus;
+
+use vl_uld::{
+    I2cAddr,
+    PlatformError,
+};
+
+use crate::{
+    bus::SharedBus,
+    transport::Transport,
+};
+
+const WRITE_BIT: u16 = 0x8000;
+
+/*
+* The SPI bus, with the chip selects of the boards on it.
+*/
+pub struct SpiCs<SPI, CS: OutputPin, const N: usize> {
+    spi: SPI,
+    cs: [CS;N],
+}
+
+impl<SPI, CS: OutputPin, const N: usize> SpiCs<SPI, CS, N> {
+    pub fn new(spi: SPI, mut cs: [CS;N]) -> Self {
+        for x in cs.iter_mut() {
+            x.set_high().expect("CS to be settable");   // none selected
+        }
+        Self{ spi, cs }
+    }
+
+    // Run 'f' with board 'i' selected. The chip select is released also if 'f' fails.
+    //
+    fn selected<R>(&mut self, i: usize, f: impl FnOnce(&mut SPI) -> R) -> R {
+        let _sel = Selected::new(&mut self.cs[i]);
+        f(&mut self.spi)
+    }
+}
+
+/*
+* Chip select held low, for as long as this lives.
+*
+* Releasing it in 'Drop' also covers an async transfer being dropped mid-way (e.g. by a timeout
+* in 'select'). Otherwise, the board would stay selected, and take part in the next transfer to
+* another board.
+*/
+struct Selected<'a, CS: OutputPin>(&'a mut CS);
+
+impl<'a, CS: OutputPin> Selected<'a, CS> {
+    fn new(cs: &'a mut CS) -> Self {
+        cs.set_low().expect("CS to be settable");
+        Self(cs)
+    }
+}
+
+impl<CS: OutputPin> Drop for Selected<'_, CS> {
+    fn drop(&mut self) {
+        self.0.set_high().expect("CS to be settable");
+    }
+}
+
+impl<SPI, CS: OutputPin, const N: usize> SharedBus<SpiCs<SPI, CS, N>> {
+    /*
+    * SPI has no bus free time to keep.
+    */
+    pub fn new_spi(spi: SPI, cs: [CS;N]) -> Self {
+        Self::with_tbuf(SpiCs::new(spi, cs), embassy_time::Duration::MIN)
+    }
+}
+
+impl<SPI: SpiBus + AsyncSpiBus, CS: OutputPin, const N: usize> Transport for SpiCs<SPI, CS, N> {
+    type Target = usize;
+
+    fn rd(&mut self, &i: &usize, index: u16, buf: &mut [u8]) -> Result<(),PlatformError> {
+        self.selected(i, |spi| {
+            SpiBus::write(spi, &(index & !WRITE_BIT).to_be_bytes())?;
+            SpiBus::read(spi, buf)?;
+            SpiBus::flush(spi)
+        }).map_err(kind)
+    }
+
+    fn wr(&mut self, &i: &usize, index: u16, vs: &[u8]) -> Result<(),PlatformError> {
+        self.selected(i, |spi| {
+            SpiBus::write(spi, &(index | WRITE_BIT).to_be_bytes())?;
+            SpiBus::write(spi, vs)?;
+            SpiBus::flush(spi)
+        }).map_err(kind)
+    }
+
+    async fn rd_async(&mut self, &i: &usize, index: u16, buf: &mut [u8]) -> Result<(),PlatformError> {
+        let Self{ spi, cs } = self;
+        let _sel = Selected::new(&mut cs[i]);
+
+        async {
+            AsyncSpiBus::write(&mut *spi, &(index & !WRITE_BIT).to_be_bytes()) .await?;
+            AsyncSpiBus::read(&mut *spi, buf) .await?;
+            AsyncSpiBus::flush(&mut *spi) .await
+        }.await
+            .map_err(kind)
+    }
+
+    fn initial(&i: &usize) -> usize {
+        i       // the chip select doesn't change
+    }
+
+    fn i2c_addr(_: &usize) -> Option<&I2cAddr> {
+        None
+    }
+
+    // Over SPI, the board is reached by its chip select; an I2C address (if one gets set, e.g.
+    // by calling the ULD directly) doesn't change that.
+    //
+    fn addr_changed(_: &mut usize, _: &I2cAddr) {}
+}
+
+// 'embedded-hal' SPI errors don't map to the I2C flavoured 'PlatformError' as well.
+fn kind(e: impl embedded_hal::spi::Error) -> PlatformError {
+    use embedded_hal::spi::ErrorKind as K;
+    match e.kind() {
+        K::Overrun => PlatformError::Overrun,
+        K::ModeFault | K::FrameFormat => PlatformError::Bus,
+        _ => PlatformError::Other,
+    }
+}
@@ -0,0 +1,139 @@
+/*
+* Driving the 'SYNC' pin of a flock of L8 boards ('flock_synced' feature).
+*
+* With the external sync enabled ('VL53::new_and_setup()' does it, for the feature), the boards
+* start each frame on a pulse on their (shared) 'SYNC' line. The pulses come from a PWM channel
+* of the MCU (e.g. 'esp-hal' LEDC), at the configured ranging frequency. The hardware keeps the
+* timing; no task is involved, so the executor's latency doesn't show in the pulses.
+*
+* The application sets up the PWM timer at the ranging frequency (we only get the channel, via
+* 'embedded-hal' 'SetDutyCycle', which doesn't reach the frequency). We set the duty cycle, for
+* the pulse width.
+*
+* The PWM runs on its own; its pulses aren't tied to 'embassy-time' instants. To align results
+* from the boards to the pulse that started them ('SyncClock'), the line is read back once, from
+* an input pin on the same net: '.clock_from_edge()'.
+*
+* Note: The pulse width gets rounded up to the PWM's duty resolution. At low frequencies, a coarse
+*       resolution (e.g. 10 bits at 1Hz: ~1ms) makes the pulse longer than needed.
+*
+* tbd. Check the pulse width against UM3109 (VL53L8CX ULD manual), on actual hardware.
+*/
+#![cfg(feature = "flock_synced")]
+
+#[cfg(feature = "defmt")]
+use defmt::debug;
+
+use core::fmt::{Display, Formatter};
+
+use embassy_time::{Duration, Instant};
+use embedded_hal::pwm::SetDutyCycle;
+use embedded_hal_async::digital::Wait;
+
+use vl_uld::RangingConfig;
+
+use crate::polling::frame_period;
+
+const PULSE_WIDTH: Duration = Duration::from_micros(100);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SyncError {
+    NotAutonomous,      // SYNC only applies to 'AUTONOMOUS' ranging
+    Pwm,                // setting the duty cycle failed
+    Pin,                // waiting on the read-back pin failed
+}
+
+impl Display for SyncError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotAutonomous => write!(f, "SYNC only applies to 'AUTONOMOUS' ranging"),
+            Self::Pwm => write!(f, "setting the SYNC duty cycle failed"),
+            Self::Pin => write!(f, "waiting for the SYNC edge failed"),
+        }
+    }
+}
+
+/*
+* The pulse schedule. Cheap to copy around (e.g. to the task receiving the results).
+*
+* Note: The PWM and 'embassy-time' are assumed to run from the same crystal; otherwise, the
+*       schedule drifts from the actual pulses over time.
+*/
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SyncClock {
+    t0: Instant,        // pulse #0
+    period: Duration,
+}
+
+impl SyncClock {
+    /*
+    * When pulse 'seq' starts (rising edge).
+    */
+    pub fn pulse_at(&self, seq: u32) -> Instant {
+        self.t0 + self.period * seq
+    }
+
+    /*
+    * The latest pulse at or before 'ts', i.e. the one that started the frame whose results were
+    * time stamped 'ts'. 'None' if 'ts' is before the first pulse.
+    */
+    pub fn pulse_before(&self, ts: Instant) -> Option<(u32, Instant)> {
+        let dt = ts.checked_duration_since(self.t0)?;
+        let seq = (dt.as_ticks() / self.period.as_ticks()) as u32;
+        Some( (seq, self.pulse_at(seq)) )
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
+
+/*
+* Generates the pulses, on a PWM channel.
+*/
+pub struct SyncGen<P: SetDutyCycle> {
+    pwm: P,
+    period: Duration,
+}
+
+impl<P: SetDutyCycle> SyncGen<P> {
+    /*
+    * 'pwm' is a channel whose timer runs at the ranging frequency of 'cfg' (the same as given to
+    * the boards). Pulses start right away.
+    */
+    pub fn new<const DIM: usize>(mut pwm: P, cfg: &RangingConfig<DIM>) -> Result<Self, SyncError> {
+        if cfg.frequency().is_none() {
+            return Err(SyncError::NotAutonomous);
+        }
+        let period = frame_period(cfg);
+
+        let max = pwm.max_duty_cycle() as u64;
+        let duty = (max * PULSE_WIDTH.as_micros()).div_ceil(period.as_micros()).clamp(1, max);
+
+        debug!("SYNC duty: {}/{}", duty, max);
+        pwm.set_duty_cycle(duty as u16).map_err(|_| SyncError::Pwm)?;
+
+        Ok( Self{ pwm, period } )
+    }
+
+    /*
+    * Wait for the next pulse on 'sense' (an input on the 'SYNC' net), and take the pulse schedule
+    * from it. That pulse is '#0'.
+    */
+    pub async fn clock_from_edge<W: Wait>(&self, sense: &mut W) -> Result<SyncClock, SyncError> {
+        sense.wait_for_rising_edge() .await
+            .map_err(|_| SyncError::Pin)?;
+
+        Ok( SyncClock{ t0: Instant::now(), period: self.period } )
+    }
+
+    /*
+    * Stop the pulses (line stays low); get the channel back.
+    */
+    pub fn stop(mut self) -> Result<P, SyncError> {
+        self.pwm.set_duty_cycle_fully_off().map_err(|_| SyncError::Pwm)?;
+        Ok(self.pwm)
+    }
+}
@@ -0,0 +1,89 @@
+/*
+* How the bytes get to a board: I2C (all sensors), or SPI with a chip select per board (L8 only;
+* see 'spi.rs').
+*
+* The ULD C code doesn't care; it reads and writes by register index. What differs is how a board
+* is reached ('Target'): by its I2C address, or by its chip select. With I2C, all boards start at
+* the same (default) address, and each is moved to its own one during the setup (that's what the
+* 'LPn' dance in 'VL53::new_flock()' is for). With chip selects, there's nothing to move.
+*
+* 'SharedBus<B>' carries the transport. 'Pl' and 'APl' (the platform, for the ULD) use it.
+*/
+use embedded_hal::i2c::{I2c, Operation};
+use embedded_hal_async::i2c::I2c as AsyncI2c;
+
+use vl_uld::{
+    DEFAULT_I2C_ADDR,
+    I2cAddr,
+    PlatformError,
+};
+
+/*
+* Implemented for any bus providing both the blocking and the async 'I2c' traits, and for 'SpiCs'.
+*/
+#[allow(async_fn_in_trait)]     // we don't require 'Send' futures (single core, Embassy)
+pub trait Transport {
+    type Target: Clone + PartialEq;     // I2C address, or chip select (board index)
+
+    fn rd(&mut self, t: &Self::Target, index: u16, buf: &mut [u8]) -> Result<(),PlatformError>;
+    fn wr(&mut self, t: &Self::Target, index: u16, vs: &[u8]) -> Result<(),PlatformError>;
+
+    async fn rd_async(&mut self, t: &Self::Target, index: u16, buf: &mut [u8]) -> Result<(),PlatformError>;
+
+    // Where a board answers, right after a reset. Compared with 't', to know whether it needs to
+    // be moved (I2C) or not.
+    //
+    fn initial(t: &Self::Target) -> Self::Target;
+
+    // The address a board is to be given, after its initialization. 'None' if not I2C.
+    //
+    fn i2c_addr(t: &Self::Target) -> Option<&I2cAddr>;
+
+    // The ULD changed the board's I2C address (see 'Platform::addr_changed()').
+    //
+    fn addr_changed(t: &mut Self::Target, addr: &I2cAddr);
+}
+
+impl<I2C: I2c + AsyncI2c> Transport for I2C {
+    type Target = I2cAddr;
+
+    fn rd(&mut self, t: &I2cAddr, index: u16, buf: &mut [u8]) -> Result<(),PlatformError> {
+        I2c::write_read(self, t.as_7bit(), &index.to_be_bytes(), buf).map_err(kind)
+    }
+
+    // 'embedded-hal' doesn't have '.write_write()', but it's easy to make one (consecutive
+    // writes are merged, without a restart).
+    //
+    fn wr(&mut self, t: &I2cAddr, index: u16, vs: &[u8]) -> Result<(),PlatformError> {
+        I2c::transaction(self, t.as_7bit(), &mut [Operation::Write(&index.to_be_bytes()), Operation::Write(&vs)]).map_err(kind)
+    }
+
+    async fn rd_async(&mut self, t: &I2cAddr, index: u16, buf: &mut [u8]) -> Result<(),PlatformError> {
+        AsyncI2c::write_read(self, t.as_7bit(), &index.to_be_bytes(), buf) .await
+            .map_err(kind)
+    }
+
+    fn initial(_: &I2cAddr) -> I2cAddr {
+        DEFAULT_I2C_ADDR        // every board starts with the default address
+    }
+
+    fn i2c_addr(t: &I2cAddr) -> Option<&I2cAddr> {
+        Some(t)
+    }
+
+    fn addr_changed(t: &mut I2cAddr, addr: &I2cAddr) {
+        *t = addr.clone();
+    }
+}
+
+// 'PlatformError' mirrors the 'embedded-hal' 'ErrorKind'.
+fn kind(e: impl embedded_hal::i2c::Error) -> PlatformError {
+    use embedded_hal::i2c::ErrorKind as K;
+    match e.kind() {
+        K::Bus => PlatformError::Bus,
+        K::ArbitrationLoss => PlatformError::ArbitrationLoss,
+        K::NoAcknowledge(_) => PlatformError::NoAcknowledge,
+        K::Overrun => PlatformError::Overrun,
+        _ => PlatformError::Other,
+    }
+}
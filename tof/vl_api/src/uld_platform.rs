@@ -1,112 +1,139 @@
 /*
 * 'Platform' implementation required by the ULD.
 *
-* For access to the I2C bus, a 'RefCell' is used. The intended use is Embassy tasks, on the same
-* priority, where multiple devices can borrow the bus, but not across 'await' boundaries.
+* Generic over the 'Transport' (I2C, or SPI for L8; 'embedded-hal' 1.0 traits) and a blocking
+* delay; any MCU with such HAL fits.
+*
+* For access to the bus, a 'SharedBus' (a 'Mutex' inside) is used. The blocking 'Pl' expects
+* the caller ('vl_api' async methods) to have locked it, before entering the ULD C code. The bus
+* also keeps the bus free time (tBUF) between transactions.
+*
+* Bus errors are logged and passed on to the ULD; they reach the application as
+* 'UldError::Platform'.
+*
+* 'APl' is the async counterpart, used for reading out the results while ranging. This way,
+* readouts don't block the Embassy executor. Initialization, starting and stopping still go via
+* the blocking 'Pl' (the ULD C code gives us no chance to yield, within those).
 */
 #[cfg(feature = "defmt")]
 #[allow(unused_imports)]
 use defmt::{info, debug, error, warn, trace, panic};
 
-use esp_hal::{
-    delay::Delay,
-    i2c::master::{I2c, Operation},
-    Blocking
-};
+use embassy_time::Timer;
+use embedded_hal::delay::DelayNs;
 
 use vl_uld::{
-    DEFAULT_I2C_ADDR,
+    AsyncPlatform,
     I2cAddr,
-    Platform
+    Platform,
+    PlatformError,
 };
 
-use core::cell::RefCell;
+use crate::{
+    bus::SharedBus,
+    transport::Transport,
+};
 
 #[cfg(feature = "defmt")]
 const TRACE_HEAD_N:usize=20;        // Number of first bytes to show
 
-#[allow(non_camel_case_types)]
-type T_NVER = ();  // ! (experimental; requires 'nightly') or Infallible
-
 /*
 */
-pub(crate) struct Pl<'a> {
-    i2c_shared: &'a RefCell<I2c<'static, Blocking>>,
-    i2c_addr: I2cAddr
+pub(crate) struct Pl<'a, B: Transport, D> {
+    bus: &'a SharedBus<B>,
+    delay: D,
+    target: B::Target
 }
 
 // Rust note: for the lifetime explanation, see:
 //  - "Lost in lifetimes" (answer)
 //      -> https://users.rust-lang.org/t/lost-with-lifetimes/82484/4?u=asko
 //
-impl<'a> Pl<'a> {
-    pub fn new(i2c_shared: &'a RefCell<I2c<'static, Blocking>>) -> Self {
+impl<'a, B: Transport, D: DelayNs> Pl<'a, B, D> {
+    /*
+    * 'target' is where the board answers now: with I2C, the default address - or the one it's
+    * been given (and retained; see 'VL53::reinit()').
+    */
+    pub fn new(bus: &'a SharedBus<B>, delay: D, target: B::Target) -> Self {
         Self{
-            i2c_shared,
-            i2c_addr: DEFAULT_I2C_ADDR     // every board starts with the default address
+            bus,
+            delay,
+            target
+        }
+    }
+
+    // Keep the bus free time, since the previous transaction (by anyone) on the bus.
+    fn wait_tbuf(&mut self) {
+        let gap = self.bus.remaining_gap();
+        if gap.as_ticks() > 0 {
+            self.delay.delay_us(gap.as_micros() as u32);
         }
     }
 }
 
-impl Platform for Pl<'_> {
+impl<B: Transport, D: DelayNs> Platform for Pl<'_, B, D> {
+    type Error = PlatformError;
+
     /*
-    * ULD reads can be in sizes of 492 bytes (or more). The 'esp-hal' requires these to be handled
-    * in multiple parts.
+    * ULD reads can be in sizes of 492 bytes (or more). Some HALs (e.g. 'esp-hal') handle these in
+    * multiple parts, internally.
     */
-    fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> Result<(),T_NVER> {     // "'!' type is experimental"
-        let mut i2c = self.i2c_shared.borrow_mut();
+    fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> Result<(),PlatformError> {
+        self.wait_tbuf();
+        let t = &self.target;
 
-        i2c.write_read(self.i2c_addr.as_7bit(), &index.to_be_bytes(), buf)
-        .unwrap_or_else(|e| {
-            panic!("I2C read at {:#06x} ({=usize} bytes) failed: {}", index, buf.len(), e);
-        });
+        let r = self.bus.with_held(|b|
+            b.rd(t, index, buf)
+        ).unwrap_or_else(busy);
+        self.bus.mark_end();
+
+        r.inspect_err(|_e| {
+            #[cfg(feature = "defmt")]
+            error!("Read at {:#06x} ({=usize} bytes) failed: {}", index, buf.len(), _e);
+        })?;
 
         #[cfg(feature = "defmt")]
         {
             if buf.len() <= TRACE_HEAD_N {
-                trace!("I2C read: {:#06x} -> {:#04x}", index, buf);
+                trace!("Read: {:#06x} -> {:#04x}", index, buf);
             } else {
-                trace!("I2C read: {:#06x} -> {:#04x}... ({} bytes)", index, slice_head(buf,TRACE_HEAD_N), buf.len());
+                trace!("Read: {:#06x} -> {:#04x}... ({} bytes)", index, slice_head(buf,TRACE_HEAD_N), buf.len());
             }
         }
 
-        // There should be 1.2ms between transactions, by the VL spec.
-        blocking_delay_us(1000);
-
         Ok(())
     }
 
     /***
     * Vendor ULD driver calls us with up to 32768 bytes, during the initialization.
     *
-    * IF we get errors from the HAL, we panic. ULD C level would often go on for too long; it's best
-    * to stop early. CERTAIN error codes MAY lead to a single retry, if we think we have a chance
-    * to recover.
+    * IF we get errors from the HAL, we return them. The ULD C code stops at the first failing
+    * transaction (mostly), and the error reaches the application.
     */
-    fn wr_bytes(&mut self, index: u16, vs: &[u8]) -> Result<(),T_NVER> {   // "'!' type is experimental" (nightly)
-        let mut i2c = self.i2c_shared.borrow_mut();
-        let addr: u8 = self.i2c_addr.as_7bit();
+    fn wr_bytes(&mut self, index: u16, vs: &[u8]) -> Result<(),PlatformError> {
+        self.wait_tbuf();
+        let t = &self.target;
+
+        let r = self.bus.with_held(|b|
+            b.wr(t, index, vs)
+        ).unwrap_or_else(busy);
+        self.bus.mark_end();
 
-        // 'esp-hal' doesn't have '.write_write()', but it's easy to make one.
-        //
-        i2c.transaction(addr, &mut [Operation::Write(&index.to_be_bytes()), Operation::Write(&vs)])
-        .unwrap_or_else(|e| {
-            panic!("I2C write to {:#06x} ({=usize} bytes) failed: {}", index, vs.len(), e);
-        });
+        r.inspect_err(|_e| {
+            #[cfg(feature = "defmt")]
+            error!("Write to {:#06x} ({=usize} bytes) failed: {}", index, vs.len(), _e);
+        })?;
 
         #[cfg(feature = "defmt")]
         {
             let n = vs.len();
             if n <= TRACE_HEAD_N {
-                trace!("I2C written: {:#06x} <- {:#04x}", index, vs);
+                trace!("Written: {:#06x} <- {:#04x}", index, vs);
             } else {
-                trace!("I2C written: {:#06x} <- {:#04x}... ({=usize} bytes)", index, slice_head(vs,TRACE_HEAD_N), n);
+                trace!("Written: {:#06x} <- {:#04x}... ({=usize} bytes)", index, slice_head(vs,TRACE_HEAD_N), n);
             }
         }
 
-        // There should be 1.3ms between transmissions, by the VL spec. (see 'tBUF', p.15)
-        blocking_delay_us(1000);    // 1300
-
         Ok(())
     }
 
@@ -114,28 +141,80 @@ impl Platform for Pl<'_> {
         #[cfg(feature = "defmt")]
         trace!("🔸 {}ms", ms);   // shows traces when the ULD code calls for delays (not for our own short ones)
 
-        blocking_delay_ms(ms);
+        self.delay.delay_ms(ms);
     }
 
     /*
     * During a ULD I2C address change, the address changes in-the-fly. We get called, once the next
     * transaction should use a new address. This is transparent to the application level.
+    *
+    * Not called over SPI (we don't change the address there).
     */
     fn addr_changed(&mut self, addr: &I2cAddr) {
-        self.i2c_addr = addr.clone();
+        B::addr_changed(&mut self.target, addr);
     }
 }
 
-fn slice_head(vs: &[u8],n_max: usize) -> &[u8] {
-    use core::cmp::min;
-    &vs[..min(vs.len(),n_max)]
+/*
+* Holds the bus locked for the duration of the (awaited) transfer; other tasks wait for it.
+*/
+pub(crate) struct APl<'a, B: Transport> {
+    bus: &'a SharedBus<B>,
+    target: B::Target
+}
+
+impl<'a, B: Transport> APl<'a, B> {
+    pub fn new(bus: &'a SharedBus<B>, target: &B::Target) -> Self {
+        Self{
+            bus,
+            target: target.clone()
+        }
+    }
+
+    pub fn bus(&self) -> &'a SharedBus<B> {
+        self.bus
+    }
+
+    #[allow(dead_code)]
+    pub fn target(&self) -> &B::Target {
+        &self.target
+    }
 }
 
-const D_PROVIDER: Delay = Delay::new();
+impl<B: Transport> AsyncPlatform for APl<'_, B> {
+    type Error = PlatformError;
+
+    async fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> Result<(),PlatformError> {
+        let mut b = self.bus.lock_async() .await;
+
+        let gap = self.bus.remaining_gap();
+        if gap.as_ticks() > 0 {
+            Timer::after(gap) .await;
+        }
+
+        let r = b.rd_async(&self.target, index, buf) .await;
+        self.bus.mark_end();
 
-fn blocking_delay_ms(ms: u32) {
-    D_PROVIDER.delay_millis(ms);
+        r.inspect_err(|_e| {
+            #[cfg(feature = "defmt")]
+            error!("Read at {:#06x} ({=usize} bytes) failed: {}", index, buf.len(), _e);
+        })?;
+
+        #[cfg(feature = "defmt")]
+        trace!("Read (async): {:#06x} -> {:#04x}... ({} bytes)", index, slice_head(buf,TRACE_HEAD_N), buf.len());
+
+        Ok(())
+    }
 }
-fn blocking_delay_us(us: u32) {
-    D_PROVIDER.delay_micros(us);
+
+// Bus not locked by us, and someone else has it (see 'SharedBus::with_held').
+fn busy<T>() -> Result<T,PlatformError> {
+    #[cfg(feature = "defmt")]
+    error!("Bus busy; not locked for the ULD call");
+    Err(PlatformError::Other)
+}
+
+fn slice_head(vs: &[u8],n_max: usize) -> &[u8] {
+    use core::cmp::min;
+    &vs[..min(vs.len(),n_max)]
 }
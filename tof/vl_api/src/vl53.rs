@@ -1,52 +1,89 @@
 /*
-* Presents one VL53 sensor, with its activation line and unique I2C address.
+* Presents one VL53 sensor, with its activation line and unique I2C address (or, for L8 on SPI,
+* its chip select).
 */
 #[cfg(feature = "defmt")]
 use defmt::debug;
 
-use core::cell::RefCell;
+use embedded_hal::delay::DelayNs;
+#[cfg(feature = "flock")]
+use embedded_hal::i2c::I2c;
+#[cfg(feature = "flock")]
+use embedded_hal_async::i2c::I2c as AsyncI2c;
+#[cfg(all(feature = "flock", feature = "vl53l8cx"))]
+use embedded_hal::spi::SpiBus;
+#[cfg(all(feature = "flock", feature = "vl53l8cx"))]
+use embedded_hal_async::spi::SpiBus as AsyncSpiBus;
+#[cfg(feature = "flock")]
+use embedded_hal::digital::{InputPin, OutputPin};
 
-use esp_hal::{
-    gpio::Input,
-    i2c::master::I2c,
-    Blocking
+#[cfg(any(feature = "single", feature = "flock"))]
+use embedded_hal_async::digital::Wait;
+
+#[cfg(feature = "xtalk")]
+use vl_uld::{
+    units::PrcU8,
+    XtalkData,
 };
-#[cfg(feature = "flock")]
-use esp_hal::gpio::Output;
 
 use vl_uld::{
     DEFAULT_I2C_ADDR,
     RangingConfig,
     Result,
     State_HP_Idle,
+    Variant,
     VL53 as VL53_ULD    // covers both L8 and L5CX
 };
+#[cfg(any(feature = "single", feature = "flock"))]
+use vl_uld::Error as UldError;
 
+#[cfg(any(feature = "single", feature = "flock"))]
+use crate::polling::{poll_interval, NoInt};
+#[cfg(feature = "flock")]
+use crate::I2cAddr;
 use crate::{
-    I2cAddr,
-    uld_platform::Pl,
+    bus::SharedBus,
+    transport::Transport,
+    uld_platform::{APl, Pl},
 };
+#[cfg(all(feature = "flock", feature = "vl53l8cx"))]
+use crate::spi::SpiCs;
 
 #[cfg(feature = "single")]
 use crate::ranging::Ranging;
 #[cfg(feature = "flock")]
-use crate::ranging_flock::RangingFlock;
+use crate::ranging_flock::{FlockInt, RangingFlock};
 
-pub struct VL53 {
-    uld: State_HP_Idle,   // initialized ULD level driver, with dedicated I2C address
+/*
+* The bus ('Transport') needs to provide both blocking and async ('embedded-hal-async') access.
+* Blocking is used by the ULD C code (init, start/stop); async for reading out the results.
+*
+* Methods touching the sensor are 'async': they first lock the 'SharedBus' (other tasks may be
+* using it), then run the - blocking - ULD C code.
+*/
+pub struct VL53<B: Transport + 'static> {
+    uld: State_HP_Idle,   // initialized ULD level driver, with dedicated I2C address (or chip select)
+    apl: APl<'static, B>
 }
 
-impl VL53 {
+impl<B: Transport + 'static> VL53<B> {
     // tbd. make so that caller can give either 'I2cAddr' or a reference
     //
-    pub fn new_and_setup(i2c_shared: &'static RefCell<I2c<'static, Blocking>>,
-        i2c_addr: &I2cAddr
+    // 'target' is the I2C address to give the board (an 'I2cAddr'), or - for SPI - its chip
+    // select index.
+    //
+    // 'delay' is a blocking one; it gets used by the ULD C code (e.g. while uploading the firmware).
+    //
+    pub async fn new_and_setup<D: DelayNs + 'static>(bus: &'static SharedBus<B>,
+        target: &B::Target,
+        delay: D,
     ) -> Result<Self> {
+        let _lock = bus.lock() .await;
 
         // The VL53L5CX doesn't retain its I2C address. Thus, we start each session by initializing
         // the firmware using the default I2C address, then changing to the requested one.
         //
-        let pl = Pl::new(i2c_shared);
+        let pl = Pl::new(bus, delay, B::initial(target));
 
         let mut uld = VL53_ULD::new_with_ping(pl)?.init()?;
 
@@ -56,67 +93,245 @@ impl VL53 {
         // when we have multiple boards to drive (that's an assumption; please prove it wrong;
         // the ULD level doesn't place such limitations, only us).
         //
+        // Mixed builds: only the L8CX boards have the SYNC pin.
+        //
         #[cfg(all(feature="flock_synced", feature = "vl53l8cx"))]
-        uld.set_sync_pin_enable(true)?;
+        if uld.variant() == Variant::L8CX {
+            uld.set_sync_pin_enable(true)?;
+        }
 
-        let a = i2c_addr;
-        if *a != DEFAULT_I2C_ADDR {
-            uld.set_i2c_address(a)?;     // tbd. '.as_8bit()' if public
+        if let Some(a) = B::i2c_addr(target) {
+            if *a != DEFAULT_I2C_ADDR {
+                uld.set_i2c_address(a)?;     // tbd. '.as_8bit()' if public
+            }
+            debug!("Board now reachable as: {}", a);
         }
-        debug!("Board now reachable as: {}", i2c_addr);
 
         Ok(Self{
             uld,
+            apl: APl::new(bus, target)
         })
     }
 
+    /*
+    * Initialize again, a board that has been reset (see 'Ranging::recover()', 'RangingFlock::reinit_board()'), ending up with the
+    * same I2C address (or chip select).
+    *
+    * After a 'PWR_EN' cycle, the board is back at the default address. With just 'LPn' toggled,
+    * it may still have the address it was given; if the default doesn't answer, we try that.
+    *
+    * tbd. Check on actual hardware, whether the 'LPn' case retains the address (or needs this).
+    */
+    #[cfg(any(feature = "single", feature = "flock"))]
+    pub(crate) async fn reinit<D: DelayNs + Clone + 'static>(bus: &'static SharedBus<B>,
+        target: &B::Target,
+        delay: D,
+    ) -> Result<Self> {
+        use vl_uld::{Error as UldError, PlatformError};
+
+        match Self::new_and_setup(bus, target, delay.clone()) .await {
+            Err(UldError::Platform(PlatformError::NoAcknowledge)) if B::initial(target) != *target => {
+                debug!("No answer at the default I2C address; trying the given one");
+
+                let _lock = bus.lock() .await;
+                let pl = Pl::new(bus, delay, target.clone());
+
+                #[allow(unused_mut)]
+                let mut uld = VL53_ULD::new_with_ping(pl)?.init()?;
+
+                #[cfg(all(feature="flock_synced", feature = "vl53l8cx"))]
+                if uld.variant() == Variant::L8CX {     // as in 'new_and_setup()'
+                    uld.set_sync_pin_enable(true)?;
+                }
+
+                Ok(Self{
+                    uld,
+                    apl: APl::new(bus, target)
+                })
+            },
+            r => r
+        }
+    }
+
     /*
     * Start ranging on a single board, with an 'INT' pin wired.
     */
     #[cfg(feature = "single")]
-    pub fn start_ranging<const DIM: usize>(self, cfg: &RangingConfig<DIM>, pinINT: Input<'static>) -> Result<Ranging<DIM>> {
-        Ranging::start(self, cfg, pinINT)
+    pub async fn start_ranging<const DIM: usize, INT: Wait>(self, cfg: &RangingConfig<DIM>, pinINT: INT) -> core::result::Result<Ranging<DIM,INT,B>, StartError<Self,INT>> {
+        Ranging::start(self, cfg, pinINT, None) .await
+    }
+
+    /*
+    * Start ranging on a single board, without 'INT'. The sensor gets polled, at an interval
+    * derived from the ranging frequency.
+    */
+    #[cfg(feature = "single")]
+    pub async fn start_ranging_polled<const DIM: usize>(self, cfg: &RangingConfig<DIM>) -> core::result::Result<Ranging<DIM,NoInt,B>, StartError<Self,NoInt>> {
+        Ranging::start(self, cfg, NoInt, Some(poll_interval(cfg))) .await
+    }
+
+    /*
+    * Cross-talk calibration, for sensors behind a cover glass. Store the results, and apply them
+    * with '.set_xtalk()' on later boots.
+    */
+    #[cfg(feature = "xtalk")]
+    pub async fn calibrate_xtalk(&mut self, reflectance: PrcU8, nb_samples: u8, distance_mm: u16) -> Result<XtalkData> {
+        let _lock = self.apl.bus().lock() .await;
+        self.uld.calibrate_xtalk(reflectance, nb_samples, distance_mm)
+    }
+
+    #[cfg(feature = "xtalk")]
+    pub async fn set_xtalk(&mut self, data: &XtalkData) -> Result<()> {
+        let _lock = self.apl.bus().lock() .await;
+        self.uld.set_xtalk(data)
+    }
+
+    /*
+    * The sensor variant, by its rev id. In mixed L5CX/L8CX builds, this tells which one the board is.
+    */
+    pub fn variant(&self) -> Variant {
+        self.uld.variant()
+    }
+
+    /*
+    * Periodic VHV recalibration during ranging (every 'n' frames; 0 = disabled). Useful if the
+    * sensor faces temperature changes.
+    */
+    pub async fn set_vhv_repeat_count(&mut self, n: u32) -> Result<()> {
+        let _lock = self.apl.bus().lock() .await;
+        self.uld.set_vhv_repeat_count(n)
+    }
+
+    /*
+    * Internal charge pump (L5CX only). Keep enabled for 2V8 AVDD; may be disabled for 3V3.
+    *
+    * Mixed builds: 'UldError::NotSupported' for an L8CX board.
+    */
+    #[cfg(feature = "vl53l5cx")]
+    pub async fn set_internal_cp_enable(&mut self, v: bool) -> Result<()> {
+        let _lock = self.apl.bus().lock() .await;
+        self.uld.set_internal_cp_enable(v)
     }
 
     /*
     * A consuming method, used when moving to "Ranging" state.
     */
-    pub(crate) fn into_uld(self) -> State_HP_Idle {
-        self.uld
+    pub(crate) fn into_parts(self) -> (State_HP_Idle, APl<'static, B>) {
+        (self.uld, self.apl)
+    }
+
+    pub(crate) fn recreate(uld: State_HP_Idle, apl: APl<'static, B>) -> Self {
+        Self { uld, apl }
     }
 
-    pub(crate) fn recreate(uld: State_HP_Idle) -> Self {
-        Self { uld }
+    #[allow(dead_code)]
+    pub(crate) fn bus(&self) -> &'static SharedBus<B> {
+        self.apl.bus()
     }
+}
 
-    #[cfg(feature = "flock")]
-    pub fn new_flock<const BOARDS: usize>(
-        LPns: [Output;BOARDS],
-        i2c_shared: &'static RefCell<I2c<'static, Blocking>>,
+#[cfg(feature = "flock")]
+impl<I2C: I2c + AsyncI2c + 'static> VL53<I2C> {
+    //
+    // 'LPns' stay with the caller; they are needed again for re-initializing a board, while ranging
+    // (see 'RangingFlock::reinit_board()').
+    //
+    pub async fn new_flock<const BOARDS: usize, LPN: OutputPin, D: DelayNs + Clone + 'static>(
+        LPns: &mut [LPN;BOARDS],
+        bus: &'static SharedBus<I2C>,
+        delay: D,
         i2c_addr_gen: impl Fn(usize) -> I2cAddr
     ) -> Result<[Self;BOARDS]> {
-        fn array_try_map_mut_enumerated<A,B, const N: usize>(mut aa: [A;N], f: impl FnMut((usize,&mut A)) -> Result<B>) -> Result<[B;N]> {
-            use arrayvec::ArrayVec;
-            let bs_av = aa.iter_mut().enumerate().map(f)
-                .collect::<Result<ArrayVec<B,N>>>();
+        use arrayvec::ArrayVec;
 
-            bs_av.map(|x| x.into_inner().ok().unwrap())
-        }
+        // Note: A plain loop, since closures (for mapping) cannot '.await'.
+        //
+        let mut vls = ArrayVec::<Self,BOARDS>::new();
 
-        let tmp: Result<[VL53;BOARDS]> = array_try_map_mut_enumerated(LPns, #[allow(non_snake_case)] |(i,LPn)| {
-            LPn.set_high();     // enable this chip and leave it on
+        for (i,LPn) in LPns.iter_mut().enumerate() {
+            LPn.set_high().expect("LPn to be settable");     // enable this chip and leave it on
 
             let i2c_addr = i2c_addr_gen(i);
             debug!("I2C ADDR: {} -> {}", i, i2c_addr);   // TEMP
-            let vl = VL53::new_and_setup(i2c_shared, &i2c_addr)?;
+            let vl = Self::new_and_setup(bus, &i2c_addr, delay.clone()) .await?;
 
             debug!("Init of board {} succeeded", i);
-            Ok(vl)
-        });
-        tmp
+            vls.push(vl);
+        }
+        Ok(vls.into_inner().ok().unwrap())
+    }
+}
+
+#[cfg(all(feature = "flock", feature = "vl53l8cx"))]
+impl<SPI: SpiBus + AsyncSpiBus + 'static, CS: OutputPin + 'static, const BOARDS: usize> VL53<SpiCs<SPI,CS,BOARDS>> {
+    //
+    // Boards on SPI are reached by their chip selects (in 'SpiCs' order); there are no addresses
+    // to hand out. Unlike with 'new_flock()', the boards' 'LPn' lines can simply be kept high.
+    //
+    pub async fn new_flock_spi<D: DelayNs + Clone + 'static>(
+        bus: &'static SharedBus<SpiCs<SPI,CS,BOARDS>>,
+        delay: D
+    ) -> Result<[Self;BOARDS]> {
+        use arrayvec::ArrayVec;
+
+        let mut vls = ArrayVec::<Self,BOARDS>::new();
+
+        for i in 0..BOARDS {
+            let vl = Self::new_and_setup(bus, &i, delay.clone()) .await?;
+
+            debug!("Init of board {} succeeded", i);
+            vls.push(vl);
+        }
+        Ok(vls.into_inner().ok().unwrap())
+    }
+}
+
+/*
+* A failed start of ranging: the reason, and what went in, back. 'vl' is the 'VL53' (for a flock,
+* see 'FlockStartError'); 'int' the 'INT' pin(s).
+*
+* Converts to 'UldError' (with '?'), for applications not wanting to keep them.
+*/
+#[cfg(any(feature = "single", feature = "flock"))]
+pub struct StartError<V, I> {
+    pub error: UldError,
+    pub vl: V,
+    pub int: I,
+}
+
+/*
+* For a flock, the 'VL53's come back only if all of them could be: a board that had already
+* started, and then failed to stop, is lost ('None'). That takes an I2C failure while handling one.
+*/
+#[cfg(feature = "flock")]
+pub type FlockStartError<const N: usize, INT, B> = StartError<Option<[VL53<B>;N]>, FlockInt<INT,N>>;
+
+#[cfg(any(feature = "single", feature = "flock"))]
+impl<V, I> core::fmt::Debug for StartError<V, I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self.error)
     }
 }
 
+#[cfg(any(feature = "single", feature = "flock"))]
+impl<V, I> core::fmt::Display for StartError<V, I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+#[cfg(all(feature = "defmt", any(feature = "single", feature = "flock")))]
+impl<V, I> defmt::Format for StartError<V, I> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", self.error)
+    }
+}
+
+#[cfg(any(feature = "single", feature = "flock"))]
+impl<V, I> From<StartError<V, I>> for UldError {
+    fn from(e: StartError<V, I>) -> Self { e.error }
+}
+
 /*
 * For multiple boards, we can extend the slice itself; this is really handy!
 *
@@ -124,14 +339,27 @@ impl VL53 {
 *       differences. The single board case doesn't need to suffer from unneeded complexity.
 */
 #[cfg(feature = "flock")]
-pub trait VLsExt<const N: usize, const DIM: usize> {
-    fn start_ranging(self, cfg: &RangingConfig<DIM>, pinINT: Input<'static>) -> Result<RangingFlock<N,DIM>>;
+#[allow(async_fn_in_trait)]     // we don't require 'Send' futures (single core, Embassy)
+pub trait VLsExt<const N: usize, const DIM: usize, B: Transport + 'static> {
+    async fn start_ranging<INT: Wait + InputPin>(self, cfg: &RangingConfig<DIM>, ints: FlockInt<INT,N>) -> core::result::Result<RangingFlock<N,DIM,INT,B>, FlockStartError<N,INT,B>>;
+    async fn start_ranging_each<INT: Wait + InputPin>(self, cfgs: &[RangingConfig<DIM>;N], ints: FlockInt<INT,N>) -> core::result::Result<RangingFlock<N,DIM,INT,B>, FlockStartError<N,INT,B>>;
+    async fn start_ranging_polled(self, cfg: &RangingConfig<DIM>) -> core::result::Result<RangingFlock<N,DIM,NoInt,B>, FlockStartError<N,NoInt,B>>;
 }
 
 #[cfg(feature = "flock")]
-impl<const N: usize, const DIM: usize> VLsExt<N,DIM> for [VL53;N] {
-    fn start_ranging(self, cfg: &RangingConfig<DIM>, pinINT: Input<'static>) -> Result<RangingFlock<N,DIM>> {
-        RangingFlock::start(self, cfg, pinINT)
+impl<const N: usize, const DIM: usize, B: Transport + 'static> VLsExt<N,DIM,B> for [VL53<B>;N] {
+    async fn start_ranging<INT: Wait + InputPin>(self, cfg: &RangingConfig<DIM>, ints: FlockInt<INT,N>) -> core::result::Result<RangingFlock<N,DIM,INT,B>, FlockStartError<N,INT,B>> {
+        RangingFlock::start(self, &core::array::from_fn(|_| cfg.clone()), ints) .await
+    }
+
+    // Config per board, in board order (e.g. front sensors faster than the side ones).
+    async fn start_ranging_each<INT: Wait + InputPin>(self, cfgs: &[RangingConfig<DIM>;N], ints: FlockInt<INT,N>) -> core::result::Result<RangingFlock<N,DIM,INT,B>, FlockStartError<N,INT,B>> {
+        RangingFlock::start(self, cfgs, ints) .await
+    }
+
+    // Without 'INT'; the boards get polled, at an interval derived from the ranging frequency.
+    async fn start_ranging_polled(self, cfg: &RangingConfig<DIM>) -> core::result::Result<RangingFlock<N,DIM,NoInt,B>, FlockStartError<N,NoInt,B>> {
+        RangingFlock::start(self, &core::array::from_fn(|_| cfg.clone()), FlockInt::Polled(poll_interval(cfg))) .await
     }
     /***
     <<
@@ -0,0 +1,47 @@
+/*
+* Types shared by the ST ToF sensor crates ('vl_uld' for the multizone L5CX/L7CX/L8CX; 'vl_l4cd'
+* for the single zone L4CD). An application driving a mix of them sees the same units, I2C
+* addresses and platform interface.
+*/
+#![no_std]
+
+mod platform;
+pub mod units;
+
+#[cfg(feature = "defmt")]
+use defmt::Format;
+
+pub use platform::{AsyncPlatform, Platform, PlatformError};
+
+/*
+* Wrapper to eliminate 8-bit vs. 7-bit I2C address misunderstandings.
+*
+* Note: Not using 'esp-hal' 'i2c::master::I2cAddress' to keep the door ever so slightly ajar for
+*       other MCU families. If someone wants to do the work.
+*/
+#[derive(Copy,Clone,Eq,PartialEq)]
+pub struct I2cAddr(u8);     // stored as 7-bit (internal detail)
+
+impl I2cAddr {
+    pub const fn from_8bit(v: u8) -> Self {
+        //assert!(v % 2 == 0 ||false, "8-bit I2C address is expected to be even");      // cannot use 'assert!' in 'const' fn's, since 'defmt' 1.0
+        //  tbd. report this to 'defmt-macros' GitHub; that 'assert!' used to work in 'const fn' context prior to 1.0
+        //      and isn't mentioned in any changelogs that it no longer should not.
+        Self(v >> 1)
+    }
+    pub fn from_7bit(v: u8) -> Self {
+        assert!(v < 0x80, "not 7-bit");
+        Self(v)
+    }
+    pub const fn as_7bit(&self) -> u8 { self.0 }      // used by platform code (needs to be 'pub')
+    //fn as_8bit(&self) -> u8 { self.0 << 1 }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for I2cAddr {
+    fn format(&self, fmt: defmt::Formatter) {
+        // 'esp-hal' (as most of the world) uses 7-bit I2C addresses, but the vendor uses 8-bit.
+        // It IS confusing, but don't want to go full 8-bit. Treating vendor as the exception!
+        defmt::write!(fmt, "{=u8:#04x}_u7", self.as_7bit());
+    }
+}
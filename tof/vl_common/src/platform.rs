@@ -0,0 +1,73 @@
+/*
+* The platform interface: how the sensor crates reach the hardware (I2C, delays).
+*/
+use core::result::Result as CoreResult;
+
+use crate::I2cAddr;
+
+/**
+* @brief App provides, to talk to the I2C and do blocking delays; provides a mechanism to inform
+*       the platform about an I2C address change.
+*
+* The L8 can also be wired for SPI. The 'index' then goes in the first two bytes of each transfer
+* (top bit set for writes), and 'addr_changed' doesn't get called.
+*
+* I2C errors are returned as the app's own 'Error' type. They pass through the ULD C code (as
+* a failed status) and reach the application as the sensor crate's 'Error::Platform'.
+*
+* Keeping the I2C bus free time ('tBUF'; 1.3us at 400kHz, 0.5us at 1MHz) between transactions is
+* up to the platform.
+*/
+pub trait Platform {
+    type Error: Into<PlatformError>;    // '()' is fine, if you don't care
+
+    // provided by the app
+    //
+    fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> CoreResult<(),Self::Error>;
+    fn wr_bytes(&mut self, index: u16, vs: &[u8]) -> CoreResult<(),Self::Error>;
+    fn delay_ms(&mut self, ms: u32);
+
+    // This is our addition (vendor API struggles with the concept). Once we have changed the I2C
+    // address the device identifies with, inform the 'Platform' struct about it.
+    //
+    fn addr_changed(&mut self, addr: &I2cAddr);
+}
+
+/**
+* @brief Async counterpart of 'Platform', for the data path.
+*
+* The ULD C code is synchronous; its calls to the platform cannot yield. We can, however, do the
+* I/O-heavy parts ourselves, before entering the C code, and let the tunnel serve them from
+* memory. E.g. with 'vl_uld' 'State_Ranging::get_data_async', the results block (~1.4kB, the bulk
+* of a readout) is read this way, and the executor keeps running other tasks meanwhile. Also the
+* data ready polls ('is_ready_async') go this way.
+*
+* Note: Initialization, starting and stopping a ranging still use the blocking 'Platform'. These
+*       interleave writes, reads and waits within a single C call (e.g. the firmware upload, in
+*       the middle of 'vl_init()'), so there's nothing to do ahead of time. Their delays
+*       ('Platform::delay_ms') block, too.
+*/
+#[allow(async_fn_in_trait)]     // we don't require 'Send' futures (single core, Embassy)
+pub trait AsyncPlatform {
+    type Error: Into<PlatformError>;
+
+    async fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> CoreResult<(),Self::Error>;
+}
+
+/*
+* What went wrong with the I2C comms. Modelled after 'embedded_hal::i2c::ErrorKind' (we don't
+* depend on 'embedded-hal', but mapping from it is 1:1).
+*/
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PlatformError {
+    Bus,
+    ArbitrationLoss,
+    NoAcknowledge,
+    Overrun,
+    Other,
+}
+
+impl From<()> for PlatformError {
+    fn from(_: ()) -> Self { Self::Other }
+}
@@ -0,0 +1,37 @@
+/*
+* build.rs
+*
+* Compiles the vendor C driver (via 'Makefile'), and links it in.
+*/
+use anyhow::*;
+
+fn main() -> Result<()> {
+    use std::{
+        env,
+        process::Command
+    };
+
+    // If IDE runs, terminate early (see 'vl_uld/build.rs').
+    if env::var("__CFBundleIdentifier").is_ok() { return Ok(()) };
+
+    let st = Command::new("make")
+        .arg("tmp/libvendor_l4cd.a")
+        .output()
+        .expect("to be able to launch `make`")   // shown if 'make' not found on PATH
+        .status;
+
+    if !st.success() {
+        panic!("[ERROR!]: Running 'make' failed. \
+            SUGGESTION: run 'make tmp/libvendor_l4cd.a' on the command line to see more error information. \
+        ");
+    }
+
+    println!("cargo:rustc-link-search=tmp");
+    println!("cargo:rustc-link-lib=static=vendor_l4cd");
+
+    // Change in Makefile (or our C header) re-triggers a build
+    println!("cargo::rerun-if-changed={}", "Makefile");
+    println!("cargo::rerun-if-changed={}", "platform.h");
+
+    Ok(())
+}
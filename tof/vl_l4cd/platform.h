@@ -0,0 +1,22 @@
+/*
+* Our 'platform.h' for the vendor VL53L4CD ULD (the vendor leaves this file to the customer).
+*
+* 'Dev_t' is ours to define. We pass a pointer to the Rust side of the platform (see
+* 'src/platform.rs'); the vendor code only hands it over to the functions below.
+*
+* The prototypes must match those in 'src/platform.rs'.
+*/
+#pragma once
+#include <stdint.h>
+
+typedef void* Dev_t;
+
+uint8_t VL53L4CD_RdDWord(Dev_t dev, uint16_t RegisterAdress, uint32_t *value);
+uint8_t VL53L4CD_RdWord(Dev_t dev, uint16_t RegisterAdress, uint16_t *value);
+uint8_t VL53L4CD_RdByte(Dev_t dev, uint16_t RegisterAdress, uint8_t *value);
+
+uint8_t VL53L4CD_WrByte(Dev_t dev, uint16_t RegisterAdress, uint8_t value);
+uint8_t VL53L4CD_WrWord(Dev_t dev, uint16_t RegisterAdress, uint16_t value);
+uint8_t VL53L4CD_WrDWord(Dev_t dev, uint16_t RegisterAdress, uint32_t value);
+
+uint8_t VL53L4CD_WaitMs(Dev_t dev, uint32_t TimeMs);
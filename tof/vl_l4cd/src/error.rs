@@ -0,0 +1,104 @@
+/*
+* Errors
+*
+* Same shape as in 'vl_uld'. The vendor status codes are fewer.
+*/
+use core::fmt::{Display, Formatter};
+
+use crate::{
+    PlatformError,
+    uld_raw::{ST_INVALID_ARGUMENT, ST_TIMEOUT, ST_XTALK_FAILED},
+};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    Uld(UldStatus),                 // ULD C API returned a non-OK status
+    Platform(PlatformError),        // I2C comms failed
+    PingMismatch{                   // sensor id not what we expected
+        expected: u16,
+        found: u16,
+    },
+    Config(ConfigError),            // 'RangingConfig' not acceptable
+    IntPin,                         // waiting on the 'INT' pin failed
+}
+
+impl Error {
+    pub(crate) fn uld(st: u8) -> Self {
+        Self::Uld(UldStatus::from(st))
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Uld(st) => write!(f, "ULD driver or hardware error: {}", st),
+            Self::Platform(e) => write!(f, "I2C communication failed: {:?}", e),
+            Self::PingMismatch{ expected, found } =>
+                write!(f, "Unexpected device: {:#06x}, expected {:#06x}", found, expected),
+            Self::Config(e) => write!(f, "Bad ranging config: {}", e),
+            Self::IntPin => write!(f, "Waiting for the INT pin failed"),
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self { Self::Config(e) }
+}
+
+/*
+* ULD C API status codes ('VL53L4CD_ERROR_...').
+*
+* Note: The vendor functions OR the statuses of their steps together ('status |= ...'), so what we
+*       get may be a mix of codes. Only the exact values are decoded; anything else is 'Other'.
+*       A '255' may thus also be '253 | 254' (and any non-zero platform status gets mixed in).
+*/
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UldStatus {
+    XtalkFailed,        // 253
+    InvalidArgument,    // 254
+    Timeout,            // 255
+    Other(u8),          // combined, or unknown
+}
+
+impl From<u8> for UldStatus {
+    fn from(v: u8) -> Self {
+        match v {
+            ST_XTALK_FAILED => Self::XtalkFailed,
+            ST_INVALID_ARGUMENT => Self::InvalidArgument,
+            ST_TIMEOUT => Self::Timeout,
+            x => Self::Other(x),
+        }
+    }
+}
+
+impl Display for UldStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::XtalkFailed => write!(f, "Xtalk calibration failed (253)"),
+            Self::InvalidArgument => write!(f, "invalid argument (254)"),
+            Self::Timeout => write!(f, "timeout, or combined errors (255)"),
+            Self::Other(x) => write!(f, "unknown or combined error ({})", x),
+        }
+    }
+}
+
+/*
+* Reasons a 'RangingConfig' is rejected. Values are the offending ones.
+*/
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    TimingBudget(u16),              // outside 10..=200 ms
+    Frequency(u8),                  // zero, or the period doesn't fit the timing budget
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TimingBudget(v) => write!(f, "timing budget out of range (10..=200ms): {}", v),
+            Self::Frequency(v) => write!(f, "frequency out of range (period must fit the timing budget): {}", v),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+/*
+* Single zone sibling of 'vl_uld': the VL53L4CD (one distance, up to ~1.3m).
+*
+* Shares the units, I2C address and 'Platform' types with 'vl_uld' (via 'vl_common'), and follows
+* its type state approach ('VL53L4CD' -> 'State_Idle' -> 'State_Ranging'). The async, 'INT'
+* driven reading (which 'vl_api' provides for the multizone sensors) is in 'Ranging'.
+*/
+#![no_std]
+#![allow(non_snake_case)]
+
+mod error;
+mod platform;
+mod ranging;
+mod state_idle;
+mod state_ranging;
+mod uld_raw;
+
+#[cfg(feature = "defmt")]
+use defmt::{debug, error};
+
+pub use {
+    error::{ConfigError, Error, UldStatus},
+    ranging::Ranging,
+    state_idle::State_Idle,
+    state_ranging::{RangingConfig, Results, State_Ranging},
+    vl_common::{units, I2cAddr, Platform, PlatformError},
+};
+
+use crate::uld_raw::VL53L4CD_SensorInit;
+
+pub type Result<T> = core::result::Result<T,Error>;
+
+// Default after each power on
+pub const DEFAULT_I2C_ADDR: I2cAddr = I2cAddr::from_8bit(0x52);
+
+const CORRECT_SENSOR_ID: u16 = 0xebaa;
+
+/*
+* Field of view, in degrees. From the datasheet.
+*/
+pub const FOV_DEG: u8 = 18;
+
+/**
+* @brief Beginning of preparing access to a single VL53L4CD sensor.
+*/
+pub struct VL53L4CD<P: Platform> {
+    p: P
+}
+
+impl<P: Platform> VL53L4CD<P> {
+    /*
+    * Like 'vl_uld::VL53::new_with_ping()', this checks there's a suitable sensor on the bus.
+    */
+    pub fn new_with_ping(/*move*/ mut p: P) -> Result<Self> {
+        match platform::read_id(&mut p).map_err(Error::Platform)? {
+            CORRECT_SENSOR_ID => {
+                #[cfg(feature="defmt")]
+                debug!("Ping succeeded: {=u16:#06x}", CORRECT_SENSOR_ID);
+                Ok(Self{ p })
+            },
+            found => {
+                #[cfg(feature="defmt")]
+                error!("Unexpected sensor id: {=u16:#06x}", found);
+                Err(Error::PingMismatch{ expected: CORRECT_SENSOR_ID, found })
+            }
+        }
+    }
+
+    /*
+    * Initialize the sensor. Unlike the multizone sensors, there's no firmware to upload; this
+    * takes some milliseconds (waiting for the boot, and a short VHV calibration).
+    *
+    * The ULD default config is a 50ms timing budget, continuous ranging.
+    */
+    pub fn init(mut self) -> Result<State_Idle<P>> {
+        platform::call(&mut self.p, |dev| unsafe { VL53L4CD_SensorInit(dev) })?;
+
+        Ok( State_Idle::new(self.p) )
+    }
+}
@@ -0,0 +1,158 @@
+/*
+* The platform object, handling ULD <-> hardware interactions.
+*
+* Simpler than in 'vl_uld': the vendor L4CD code keeps no state of its own, and 'Dev_t' (the
+* handle it passes around) is ours to define. It's a pointer to a 'Dev', living on the stack for
+* the duration of a single ULD C API call.
+*/
+#![allow(non_snake_case)]
+
+#[cfg(feature = "defmt")]
+use defmt::warn;
+
+use core::{
+    ptr,
+    result::Result as CoreResult,
+};
+
+use crate::{
+    Error,
+    I2cAddr,
+    Platform,
+    PlatformError,
+    Result,
+    uld_raw::{Dev_t, ST_OK},
+};
+
+const ST_ERR: u8 = u8::MAX;     // the vendor platform code returns this on I2C errors
+
+/*
+* The tunnel, from the ULD C code's point of view. Like in 'vl_uld', this takes generics
+* ('P: Platform') out of the picture, for the '#[unsafe(no_mangle)]' functions.
+*/
+pub(crate) trait Tunnel {
+    fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> CoreResult<(),PlatformError>;
+    fn wr_bytes(&mut self, index: u16, vs: &[u8]) -> CoreResult<(),PlatformError>;
+    fn delay_ms(&mut self, ms: u32);
+}
+
+impl<P: Platform> Tunnel for P {
+    fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> CoreResult<(),PlatformError> {
+        Platform::rd_bytes(self, index, buf).map_err(Into::into)
+    }
+    fn wr_bytes(&mut self, index: u16, vs: &[u8]) -> CoreResult<(),PlatformError> {
+        Platform::wr_bytes(self, index, vs).map_err(Into::into)
+    }
+    fn delay_ms(&mut self, ms: u32) { Platform::delay_ms(self, ms) }
+}
+
+/*
+* What 'Dev_t' points to. Only the first platform error (within a call) is kept; the rest are
+* likely consequences.
+*/
+struct Dev<'a> {
+    t: &'a mut dyn Tunnel,
+    pending: Option<PlatformError>,
+}
+
+/*
+* Make a ULD C API call, with 'p' reachable from the platform functions (below). A failed status
+* becomes an 'Error'; if the tunnel saw an I2C error, that's the cause.
+*/
+pub(crate) fn call<P: Platform>(p: &mut P, f: impl FnOnce(Dev_t) -> u8) -> Result<()> {
+    let mut dev = Dev{ t: p, pending: None };
+
+    match f(ptr::from_mut(&mut dev) as Dev_t) {
+        ST_OK => Ok(()),
+        st => Err(match dev.pending {
+            Some(e) => Error::Platform(e),
+            None => Error::uld(st)
+        })
+    }
+}
+
+/*
+* The sensor id, read without involving the vendor code (like 'vl_uld' does its ping).
+*/
+pub(crate) fn read_id<P: Platform>(p: &mut P) -> CoreResult<u16,PlatformError> {
+    let mut buf = [0_u8;2];
+    Platform::rd_bytes(p, 0x010f, &mut buf).map_err(Into::into)?;   // 'VL53L4CD_IDENTIFICATION__MODEL_ID'
+    Ok( u16::from_be_bytes(buf) )
+}
+
+/*
+* Inform the platform of a changed I2C address (after 'VL53L4CD_SetI2CAddress()' succeeded).
+*/
+pub(crate) fn addr_changed<P: Platform>(p: &mut P, addr: &I2cAddr) {
+    Platform::addr_changed(p, addr)
+}
+
+fn with<T>(dev: Dev_t, f: impl FnOnce(&mut dyn Tunnel) -> CoreResult<T,PlatformError>) -> CoreResult<T,u8> {
+    let dev = unsafe { &mut *(dev as *mut Dev) };   // valid for the duration of 'call()'
+
+    f(dev.t).map_err(|e| {
+        #[cfg(feature = "defmt")]
+        warn!("Platform error: {}", e);
+
+        if dev.pending.is_none() {
+            dev.pending = Some(e);
+        }
+        ST_ERR
+    })
+}
+
+fn status(r: CoreResult<(),u8>) -> u8 {
+    r.err().unwrap_or(ST_OK)
+}
+
+/*
+* Raw part of interfacing.
+*
+* These functions are called by the vendor C code, passing control back to Rust. Multi-byte
+* values are big endian, on the wire.
+*
+* Obviously: DO NOT CHANGE THE PROTOTYPES. They must match with our 'platform.h'.
+*/
+#[unsafe(no_mangle)]
+pub extern "C" fn VL53L4CD_RdByte(dev: Dev_t, index: u16, p_value: *mut u8) -> u8 {
+    let mut buf = [0_u8;1];
+    status( with(dev, |t| t.rd_bytes(index, &mut buf))
+        .map(|_| unsafe { *p_value = buf[0] }) )
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn VL53L4CD_RdWord(dev: Dev_t, index: u16, p_value: *mut u16) -> u8 {
+    let mut buf = [0_u8;2];
+    status( with(dev, |t| t.rd_bytes(index, &mut buf))
+        .map(|_| unsafe { *p_value = u16::from_be_bytes(buf) }) )
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn VL53L4CD_RdDWord(dev: Dev_t, index: u16, p_value: *mut u32) -> u8 {
+    let mut buf = [0_u8;4];
+    status( with(dev, |t| t.rd_bytes(index, &mut buf))
+        .map(|_| unsafe { *p_value = u32::from_be_bytes(buf) }) )
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn VL53L4CD_WrByte(dev: Dev_t, index: u16, v: u8) -> u8 {
+    status( with(dev, |t| t.wr_bytes(index, &[v])) )
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn VL53L4CD_WrWord(dev: Dev_t, index: u16, v: u16) -> u8 {
+    status( with(dev, |t| t.wr_bytes(index, &v.to_be_bytes())) )
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn VL53L4CD_WrDWord(dev: Dev_t, index: u16, v: u32) -> u8 {
+    status( with(dev, |t| t.wr_bytes(index, &v.to_be_bytes())) )
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn VL53L4CD_WaitMs(dev: Dev_t, time_ms: u32) -> u8 {
+    status( with(dev, |t| {
+        t.delay_ms(time_ms);
+        Ok(())
+    }) )
+}
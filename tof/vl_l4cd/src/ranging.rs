@@ -0,0 +1,57 @@
+/*
+* Async, 'INT' driven reading of results. Like 'vl_api::Ranging', but for a single zone sensor.
+*
+* The sensor pulls 'GPIO1' low when results are ready (active low is the vendor default), until
+* we clear the interrupt (in 'State_Ranging::get_data()').
+*
+* tbd. 'vl_api::SharedBus' (tBUF tracking, sharing the bus with other tasks) would be good to use
+*      here, too. It would need to move to a crate that doesn't depend on 'vl_uld' ('vl_common'
+*      would gain 'embassy-sync' and 'embedded-hal' deps).
+*/
+use embassy_time::Instant;
+use embedded_hal_async::digital::Wait;
+
+use crate::{
+    Error,
+    Platform,
+    RangingConfig,
+    Result,
+    Results,
+    State_Idle,
+    State_Ranging,
+};
+
+pub struct Ranging<P: Platform, INT: Wait> {
+    uld: State_Ranging<P>,
+    pinINT: INT,
+}
+
+impl<P: Platform, INT: Wait> Ranging<P, INT> {
+    pub fn start(vl: State_Idle<P>, cfg: &RangingConfig, pinINT: INT) -> Result<Self> {
+        let uld = vl.start_ranging(cfg)?;
+        Ok(Self{ uld, pinINT })
+    }
+
+    /*
+    * Wait for the next results. The 'Instant' is taken right after seeing 'INT' low, i.e. close
+    * after the measurement.
+    *
+    * 'INT' stays low until the interrupt is cleared, so results that came while we weren't
+    * listening are provided right away (with a later time stamp).
+    *
+    * Note: Reading the results blocks the executor for the (short) I2C transactions.
+    */
+    pub async fn get_data(&mut self) -> Result<(Results, Instant)> {
+        self.pinINT.wait_for_low().await
+            .map_err(|_| Error::IntPin)?;
+        let time_stamp = Instant::now();
+
+        let res = self.uld.get_data()?;
+        Ok((res, time_stamp))
+    }
+
+    pub fn stop(self) -> Result<(State_Idle<P>, INT)> {
+        let vl = self.uld.stop()?;
+        Ok((vl, self.pinINT))
+    }
+}
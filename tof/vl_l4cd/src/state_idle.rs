@@ -0,0 +1,44 @@
+/*
+* Idle state: sensor initialized, not ranging.
+*/
+use crate::{
+    platform,
+    I2cAddr,
+    Platform,
+    RangingConfig,
+    Result,
+    State_Ranging,
+    uld_raw::{VL53L4CD_SetI2CAddress, VL53L4CD_SetRangeTiming, VL53L4CD_StartRanging},
+};
+
+pub struct State_Idle<P: Platform> {
+    p: P
+}
+
+impl<P: Platform> State_Idle<P> {
+    pub(crate) fn new(p: P) -> Self {
+        Self{ p }
+    }
+
+    /*
+    * Change the I2C address the sensor responds to. Lost at power off (or 'XSHUT' low).
+    */
+    pub fn set_i2c_address(&mut self, addr: &I2cAddr) -> Result<()> {
+        platform::call(&mut self.p, |dev| unsafe {
+            VL53L4CD_SetI2CAddress(dev, addr.as_7bit() << 1)    // vendor takes 8-bit
+        })?;
+        platform::addr_changed(&mut self.p, addr);
+        Ok(())
+    }
+
+    pub fn start_ranging(mut self, cfg: &RangingConfig) -> Result<State_Ranging<P>> {
+        let (budget_ms, inter_ms) = cfg.validate()?;
+
+        platform::call(&mut self.p, |dev| unsafe {
+            VL53L4CD_SetRangeTiming(dev, budget_ms as u32, inter_ms as u32)
+        })?;
+        platform::call(&mut self.p, |dev| unsafe { VL53L4CD_StartRanging(dev) })?;
+
+        Ok( State_Ranging::new(self.p) )
+    }
+}
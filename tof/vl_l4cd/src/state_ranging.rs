@@ -0,0 +1,145 @@
+/*
+* Ranging state: results come in, at the configured pace.
+*/
+use crate::{
+    platform,
+    units::{HzU8, MsU16},
+    ConfigError,
+    Platform,
+    Result,
+    State_Idle,
+    uld_raw::{
+        VL53L4CD_CheckForDataReady,
+        VL53L4CD_ClearInterrupt,
+        VL53L4CD_GetResult,
+        VL53L4CD_ResultsData_t,
+        VL53L4CD_StopRanging,
+    },
+};
+
+/*
+* Configuration for a ranging session.
+*
+* Without a frequency, ranging is continuous: a new measurement starts as soon as the previous one
+* is done (one per timing budget). With a frequency, the sensor measures once per period, and idles
+* in between (the period must fit the timing budget).
+*/
+#[derive(Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RangingConfig {
+    timing_budget: MsU16,
+    frequency: Option<HzU8>,
+}
+
+impl Default for RangingConfig {
+    fn default() -> Self {
+        Self{ timing_budget: MsU16(50), frequency: None }   // ULD defaults
+    }
+}
+
+impl RangingConfig {
+    pub fn with_timing_budget(self, v: MsU16) -> Self {
+        Self{ timing_budget: v, ..self }
+    }
+
+    pub fn with_frequency(self, v: HzU8) -> Self {
+        Self{ frequency: Some(v), ..self }
+    }
+
+    pub fn frequency(&self) -> Option<HzU8> {
+        self.frequency
+    }
+
+    /*
+    * '(timing budget, inter measurement)' in ms, for the ULD. Inter measurement 0 means continuous.
+    */
+    pub(crate) fn validate(&self) -> core::result::Result<(u16,u16),ConfigError> {
+        let budget = self.timing_budget.0;
+        if !(10..=200).contains(&budget) {
+            Err(ConfigError::TimingBudget(budget))?;
+        }
+
+        let inter = match self.frequency {
+            None => 0,
+            Some(HzU8(0)) => Err(ConfigError::Frequency(0))?,
+            Some(HzU8(f)) => {
+                let ms = 1000 / (f as u16);
+                if ms <= budget {
+                    Err(ConfigError::Frequency(f))?;
+                }
+                ms
+            }
+        };
+        Ok((budget, inter))
+    }
+}
+
+/*
+* One measurement.
+*
+* 'range_status' is as in the vendor's UM2931: 0 = valid; 1..=4 can still be usable (e.g. 2:
+* signal below threshold); see the user manual for the rest.
+*/
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Results {
+    pub range_status: u8,
+    pub distance_mm: u16,
+    pub sigma_mm: u16,
+    pub signal_rate_kcps: u16,
+    pub ambient_rate_kcps: u16,
+    pub number_of_spad: u16,
+}
+
+impl Results {
+    pub fn is_valid(&self) -> bool {
+        self.range_status == 0
+    }
+}
+
+impl From<&VL53L4CD_ResultsData_t> for Results {
+    fn from(r: &VL53L4CD_ResultsData_t) -> Self {
+        Self{
+            range_status: r.range_status,
+            distance_mm: r.distance_mm,
+            sigma_mm: r.sigma_mm,
+            signal_rate_kcps: r.signal_rate_kcps,
+            ambient_rate_kcps: r.ambient_rate_kcps,
+            number_of_spad: r.number_of_spad,
+        }
+    }
+}
+
+pub struct State_Ranging<P: Platform> {
+    p: P
+}
+
+impl<P: Platform> State_Ranging<P> {
+    pub(crate) fn new(p: P) -> Self {
+        Self{ p }
+    }
+
+    pub fn is_ready(&mut self) -> Result<bool> {
+        let mut ready: u8 = 0;
+        platform::call(&mut self.p, |dev| unsafe { VL53L4CD_CheckForDataReady(dev, &mut ready) })?;
+        Ok(ready != 0)
+    }
+
+    /*
+    * Read the results, and let the sensor know it can raise 'INT' for the next ones.
+    *
+    * Call only once 'is_ready()' (or the 'INT' line) tells there are results.
+    */
+    pub fn get_data(&mut self) -> Result<Results> {
+        let mut raw = VL53L4CD_ResultsData_t::default();
+        platform::call(&mut self.p, |dev| unsafe { VL53L4CD_GetResult(dev, &mut raw) })?;
+        platform::call(&mut self.p, |dev| unsafe { VL53L4CD_ClearInterrupt(dev) })?;
+
+        Ok( Results::from(&raw) )
+    }
+
+    pub fn stop(mut self) -> Result<State_Idle<P>> {
+        platform::call(&mut self.p, |dev| unsafe { VL53L4CD_StopRanging(dev) })?;
+        Ok( State_Idle::new(self.p) )
+    }
+}
@@ -0,0 +1,40 @@
+/*
+* Rust side of the vendor 'VL53L4CD_api.h' (the parts we use).
+*
+* Kept by hand, instead of 'bindgen': the API is small, and the (our) 'Dev_t' is just a pointer.
+* Must match the C prototypes; remaining in sync is not enforced.
+*/
+#![allow(non_camel_case_types)]
+
+use core::ffi::c_void;
+
+pub type Dev_t = *mut c_void;   // see 'platform.h'
+
+pub const ST_OK: u8 = 0;                    // 'VL53L4CD_ERROR_NONE'
+pub const ST_XTALK_FAILED: u8 = 253;        // 'VL53L4CD_ERROR_XTALK_FAILED'
+pub const ST_INVALID_ARGUMENT: u8 = 254;    // 'VL53L4CD_ERROR_INVALID_ARGUMENT'
+pub const ST_TIMEOUT: u8 = 255;             // 'VL53L4CD_ERROR_TIMEOUT'
+
+#[repr(C)]
+#[derive(Default)]
+pub struct VL53L4CD_ResultsData_t {
+    pub range_status: u8,
+    pub distance_mm: u16,
+    pub ambient_rate_kcps: u16,
+    pub ambient_per_spad_kcps: u16,
+    pub signal_rate_kcps: u16,
+    pub signal_per_spad_kcps: u16,
+    pub number_of_spad: u16,
+    pub sigma_mm: u16,
+}
+
+unsafe extern "C" {
+    pub fn VL53L4CD_SetI2CAddress(dev: Dev_t, new_address: u8 /*8-bit*/) -> u8;
+    pub fn VL53L4CD_SensorInit(dev: Dev_t) -> u8;
+    pub fn VL53L4CD_SetRangeTiming(dev: Dev_t, timing_budget_ms: u32, inter_measurement_ms: u32) -> u8;
+    pub fn VL53L4CD_StartRanging(dev: Dev_t) -> u8;
+    pub fn VL53L4CD_StopRanging(dev: Dev_t) -> u8;
+    pub fn VL53L4CD_CheckForDataReady(dev: Dev_t, p_is_data_ready: *mut u8) -> u8;
+    pub fn VL53L4CD_ClearInterrupt(dev: Dev_t) -> u8;
+    pub fn VL53L4CD_GetResult(dev: Dev_t, p_result: *mut VL53L4CD_ResultsData_t) -> u8;
+}
@@ -9,15 +9,36 @@ use anyhow::*;
 
 include!("../build_snippets/pins.in");  // process_pins()
 
-// 'X' is either 5 or 8
-const X: u8 =
-         if cfg!(feature = "vl53l8cx") { 8 }
-    else if cfg!(feature = "vl53l5cx") { 5 }
-    else { 0 };     // an error will be produced, later
+// ULD variants to build: one of 5, 7 or 8 - or both 5 and 8 ("mixed" build; L5CX and L8CX sensors
+// in the same project). Other combinations produce an error, later.
+fn variants() -> Vec<u8> {
+    #[allow(unused_mut)]
+    let mut v = vec!();
+    #[cfg(feature = "vl53l5cx")]
+    v.push(5);
+    #[cfg(feature = "vl53l7cx")]
+    v.push(7);
+    #[cfg(feature = "vl53l8cx")]
+    v.push(8);
+    v
+}
 
 #[allow(non_snake_case)]
 const CONFIG_H_NEXT: &str = "tmp/config58.h.next";
 
+// Vendor plugins to compile in (names as in 'vl53l{578}cx_plugin_{name}.c')
+fn plugins() -> Vec<&'static str> {
+    #[allow(unused_mut)]
+    let mut v = vec!();
+    #[cfg(feature = "xtalk")]
+    v.push("xtalk");
+    #[cfg(feature = "detection_thresholds")]
+    v.push("detection_thresholds");
+    #[cfg(feature = "motion_indicator")]
+    v.push("motion_indicator");
+    v
+}
+
 const PINS_OUT_FN: &str = "tmp/pins_snippet.in";
 
 /*
@@ -44,6 +65,27 @@ fn main() -> Result<()> {
     // If IDE runs, terminate early.
     if IDE_RUN { return Ok(()) };
 
+    // Host build (e.g. 'cargo test --target x86_64-unknown-linux-gnu'), for running against the
+    // simulated sensor ('sim' feature). The C library and bindings are made for the host, in
+    // 'tmp/host/', and the MCU link arguments are left out.
+    //
+    #[allow(non_snake_case)]
+    let HOST: bool = !env::var("TARGET").expect("TARGET to have a value")
+        .starts_with("riscv32");
+
+    let out_dir = if HOST { "tmp/host" } else { "tmp" };
+
+    println!("cargo::rustc-check-cfg=cfg(uld_host)");
+    if HOST {
+        println!("cargo::rustc-cfg=uld_host");
+    }
+
+    // The simulated sensor needs 'std'.
+    #[cfg(feature = "sim")]
+    if !HOST {
+        panic!("📍 Feature 'sim' is for host builds; e.g. 'cargo test --target x86_64-unknown-linux-gnu --features=sim,...'");
+    }
+
     // DEBUG: Show what we know about the compilation.
     //
     // <<
@@ -94,12 +136,27 @@ fn main() -> Result<()> {
         //R #[cfg(all(feature = "range_sigma_mm", not(feature = "distance_mm")))]
         //R println!("cargo:warning=Feature 'range_sigma_mm' does not make sense without feature 'distance_mm' (which is not enabled)");
 
-        // One sensor type, per project
-        #[cfg(not(any(feature = "vl53l5cx", feature = "vl53l8cx")))]
-        panic!("📍 Must enable feature: {{vl53l5cx|vl53l8cx}}");
+        // One sensor type per project; except that L5CX and L8CX can be mixed (the variant is then
+        // picked per sensor, by its rev id).
+        #[cfg(not(any(feature = "vl53l5cx", feature = "vl53l7cx", feature = "vl53l8cx")))]
+        panic!("📍 Must enable feature: {{vl53l5cx|vl53l7cx|vl53l8cx}}");
 
-        #[cfg(all(feature = "vl53l5cx", feature = "vl53l8cx"))]
-        panic!("📍 Must enable ONLY one of features: {{vl53l5cx|vl53l8cx}}");
+        #[cfg(any(
+            all(feature = "vl53l5cx", feature = "vl53l7cx"),
+            all(feature = "vl53l7cx", feature = "vl53l8cx")
+        ))]
+        panic!("📍 Must enable ONLY one of features: {{vl53l5cx|vl53l7cx|vl53l8cx}} (or both 'vl53l5cx' and 'vl53l8cx')");
+    }
+
+    // Mixed L5CX/L8CX build: both ULD C libraries are linked in, with their symbols prefixed (see
+    // 'Makefile'), and the Rust side picks one per sensor.
+    //
+    #[allow(non_snake_case)]
+    let MIXED: bool = variants().len() > 1;
+
+    println!("cargo::rustc-check-cfg=cfg(uld_mixed)");
+    if MIXED {
+        println!("cargo::rustc-cfg=uld_mixed");
     }
 
     // EXAMPLE config sanity checks.
@@ -137,9 +194,11 @@ fn main() -> Result<()> {
     //      'WIRING.md' and 'build_snippets/'. Ideas, opinions? #feedback
     {
         #[cfg(feature="vl53l8cx")]
-        const SENSOR_ID: &str = "vl53l8";   // without "cx"
-        #[cfg(feature="vl53l5cx")]
+        const SENSOR_ID: &str = "vl53l8";   // without "cx"; also for mixed builds
+        #[cfg(all(feature="vl53l5cx", not(feature="vl53l8cx")))]
         const SENSOR_ID: &str = "vl53l5cx";
+        #[cfg(feature="vl53l7cx")]
+        const SENSOR_ID: &str = "vl53l7cx";
 
         const PINS_TOML: &str = "../pins.toml";
 
@@ -210,9 +269,16 @@ fn main() -> Result<()> {
         #[cfg(not(feature = "signal_per_spad"))]
         add!("DISABLE_SIGNAL_PER_SPAD");
 
-        // 'motion_indicator' support is not implemented; always disable in C
+        // Motion indicator needs also the plugin (see 'plugins()'); both are steered by the same feature.
+        #[cfg(not(feature = "motion_indicator"))]
         add!("DISABLE_MOTION_INDICATOR");
 
+        // Plugins (separate C files in the vendor package); makes their headers visible to 'wrap58.h'.
+        //
+        for p in plugins() {
+            add!(format!("PLUGIN_{}", p.to_uppercase()));
+        }
+
         // Vendor docs:
         //      "the number of target[s] per zone sent through I2C. [...] a lower number [...] means a lower RAM
         //      [consumption]."
@@ -242,32 +308,40 @@ fn main() -> Result<()> {
 
     // make stuff
     //
-    let st = Command::new("make")
-        //.arg("-B")
-        .arg( format!("tmp/libvendor_uld{X}.a") )    // ULD C library
-        .arg( format!("tmp/uld_raw{X}.rs") )      // generate the ULD Rust bindings
-        .arg( format!("X={X}") )
-        .output()
-        .expect("to be able to launch `make`")   // shown if 'make' not found on PATH
-        .status;
-
-    if !st.success() {
-        // Remove "tmp/config[.next].h" on failure. This tries to avoid an awkward situation where
-        // the developer needs to remove them, themselves.
-        //
-        // tbd. what's the right thing to do? #undecided
-        //
-        //fs::remove_file(CONFIG_H)?;
-        //fs::remove_file(CONFIG_H_NEXT)?;
-
-        panic!("[ERROR!]: Running 'make' failed. \
-            SUGGESTION: run 'make manual X={X}' on the command line to see more error information. \
-        ");
+    // Note: Mixed builds run 'make' once per variant. The runs share 'tmp/c_src/' (and thus need to
+    //      be sequential).
+    //
+    for x in variants() {
+        let st = Command::new("make")
+            //.arg("-B")
+            .arg( format!("{out_dir}/libvendor_uld{x}.a") )    // ULD C library
+            .arg( format!("{out_dir}/uld_raw{x}.rs") )      // generate the ULD Rust bindings
+            .arg( format!("X={x}") )
+            .arg( format!("PLUGINS={}", plugins().join(" ")) )
+            .arg( format!("NATIVE={}", if HOST { "1" } else { "" }) )  // not 'HOST'; Cargo sets that in our env
+            .arg( format!("MIXED={}", if MIXED { "1" } else { "" }) )
+            .output()
+            .expect("to be able to launch `make`")   // shown if 'make' not found on PATH
+            .status;
+
+        if !st.success() {
+            // Remove "tmp/config[.next].h" on failure. This tries to avoid an awkward situation where
+            // the developer needs to remove them, themselves.
+            //
+            // tbd. what's the right thing to do? #undecided
+            //
+            //fs::remove_file(CONFIG_H)?;
+            //fs::remove_file(CONFIG_H_NEXT)?;
+
+            panic!("[ERROR!]: Running 'make' failed. \
+                SUGGESTION: run 'make manual X={x}{}{}' on the command line to see more error information. \
+            ", if HOST { " NATIVE=1" } else { "" }, if MIXED { " MIXED=1" } else { "" });
+        }
     }
 
-    // Link arguments
+    // Link arguments (MCU only)
     //
-    {
+    if !HOST {
         for s in [
             "-Tlinkall.x",
             "-Tdefmt.x"     // required by 'defmt'
@@ -280,8 +354,10 @@ fn main() -> Result<()> {
         }
     }
 
-    println!("cargo:rustc-link-search=tmp");
-    println!("cargo:rustc-link-lib=static=vendor_uld{}", X);
+    println!("cargo:rustc-link-search={out_dir}");
+    for x in variants() {
+        println!("cargo:rustc-link-lib=static=vendor_uld{}", x);
+    }
 
     // Change in Makefile re-triggers a build
     println!("cargo::rerun-if-changed={}", "Makefile");
@@ -35,6 +35,8 @@ impl MyPlatform {
 type NEVER = ();  // "'!' type is experimental"; requires nightly
 
 impl Platform for MyPlatform {
+    type Error = NEVER;
+
     /*
     */
     fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> Result<(),NEVER> {
@@ -53,6 +55,7 @@ impl Platform for MyPlatform {
             trace!("I2C read: {:#06x} -> {:#04x}... ({} bytes)", index, slice_head(buf,TRACE_SLICE_HEAD), buf.len());
         }
 
+        blocking_delay_us(TBUF_US);
         Ok(())
     }
 
@@ -84,6 +87,7 @@ impl Platform for MyPlatform {
             trace!("I2C written: {:#06x} <- {:#04x}... ({=usize} bytes)", index, slice_head(vs,TRACE_SLICE_HEAD), n);
         }
 
+        blocking_delay_us(TBUF_US);
         Ok(())
     }
 
@@ -104,6 +108,9 @@ fn slice_head(vs: &[u8],n_max: usize) -> &[u8] {
 
 const D_PROVIDER: Delay = Delay::new();
 
+// I2C bus free time between transactions ('tBUF'): 1.3us at 400kHz. The 'Platform' needs to keep it.
+const TBUF_US: u32 = 2;
+
 fn blocking_delay_us(us: u32) {
     D_PROVIDER.delay_micros(us);
 }
@@ -0,0 +1,54 @@
+/*
+* DCI indices
+*
+* The sensor's firmware parameters live in its "DCI" memory. The ULD C API writes them e.g. when
+* setting the ranging frequency, but there are also parameters that don't have an API function
+* (e.g. the glare filter on L5CX).
+*
+* These are UNDOCUMENTED by the vendor. The indices below are the ones used by the ULD C code
+* itself; the data sizes and meanings need to be checked from there.
+*
+*   Usage:
+*   <<
+*       let mut buf = Aligned([0_u8; 8]);
+*       vl.dci_read_data(dci::PIPE_CONTROL, &mut buf.0)?;
+*   <<
+*/
+#![cfg(feature = "dci")]
+
+use crate::uld_raw;
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DciIndex(pub u16);
+
+pub const ZONE_CONFIG: DciIndex = DciIndex(uld_raw::DCI_ZONE_CONFIG);
+pub const FREQ_HZ: DciIndex = DciIndex(uld_raw::DCI_FREQ_HZ);
+pub const INT_TIME: DciIndex = DciIndex(uld_raw::DCI_INT_TIME);
+pub const FW_NB_TARGET: DciIndex = DciIndex(uld_raw::DCI_FW_NB_TARGET);
+pub const RANGING_MODE: DciIndex = DciIndex(uld_raw::DCI_RANGING_MODE);
+pub const DSS_CONFIG: DciIndex = DciIndex(uld_raw::DCI_DSS_CONFIG);
+pub const TARGET_ORDER: DciIndex = DciIndex(uld_raw::DCI_TARGET_ORDER);
+pub const SHARPENER: DciIndex = DciIndex(uld_raw::DCI_SHARPENER);
+pub const MOTION_DETECTOR_CFG: DciIndex = DciIndex(uld_raw::DCI_MOTION_DETECTOR_CFG);
+pub const SINGLE_RANGE: DciIndex = DciIndex(uld_raw::DCI_SINGLE_RANGE);
+pub const OUTPUT_CONFIG: DciIndex = DciIndex(uld_raw::DCI_OUTPUT_CONFIG);
+pub const OUTPUT_ENABLES: DciIndex = DciIndex(uld_raw::DCI_OUTPUT_ENABLES);
+pub const OUTPUT_LIST: DciIndex = DciIndex(uld_raw::DCI_OUTPUT_LIST);
+pub const PIPE_CONTROL: DciIndex = DciIndex(uld_raw::DCI_PIPE_CONTROL);
+
+// Variant specific; in mixed builds, use them only with the right sensor (see '.variant()').
+#[cfg(feature = "vl53l5cx")]
+pub const INTERNAL_CP: DciIndex = DciIndex(crate::uld_raw5::DCI_INTERNAL_CP);
+#[cfg(feature = "vl53l5cx")]
+pub const GLARE_FILTER: DciIndex = DciIndex(crate::uld_raw5::GLARE_FILTER);
+
+#[cfg(feature = "vl53l8cx")]
+pub const SYNC_PIN: DciIndex = DciIndex(crate::uld_raw8::DCI_SYNC_PIN);
+
+/*
+* 'dci_write_data' (C) swaps the bytes of the buffer in place (and back), 4 bytes at a time. The
+* swap needs the buffer to be 'u32' aligned. Wrapping the data in this makes sure it is.
+*/
+#[repr(C, align(4))]
+pub struct Aligned<const N: usize>(pub [u8;N]);
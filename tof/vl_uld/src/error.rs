@@ -0,0 +1,173 @@
+/*
+* Errors
+*
+* The ULD C API returns a single status byte. Its values are documented only loosely (see
+* 'wrap58.h'), and the C code ORs together statuses of consecutive operations. We decode the
+* ones that make sense on their own; the rest are kept as 'Other'.
+*
+* I2C failures are reported as 'Platform', also when they happen within a ULD C API call (the
+* tunnel keeps the error aside; see 'platform.rs').
+*/
+use core::fmt::{Display, Formatter};
+
+use crate::{
+    platform::PlatformError,
+    uld_raw::ST_ERROR,
+};
+#[cfg(uld_mixed)]
+use crate::Variant;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    Uld(UldStatus),                 // ULD C API returned a non-OK status
+    Platform(PlatformError),        // I2C comms failed
+    PingMismatch{                   // '(device id, rev id)' not what we expected
+        expected: (u8,u8),
+        found: (u8,u8),
+    },
+    Config(ConfigError),            // 'RangingConfig' not acceptable
+    #[cfg(feature = "dci")]
+    Dci(DciError),                  // DCI buffer not acceptable; nothing was sent
+    #[cfg(uld_mixed)]
+    NotSupported(Variant),          // mixed builds: the call doesn't apply to this sensor (e.g. SYNC pin on L5CX)
+}
+
+impl Error {
+    pub(crate) fn uld(st: u8) -> Self {
+        Self::Uld(UldStatus::from(st))
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Uld(st) => write!(f, "ULD driver or hardware error: {}", st),
+            Self::Platform(e) => write!(f, "I2C communication failed: {:?}", e),
+            Self::PingMismatch{ expected: (a,b), found: (c,d) } =>
+                write!(f, "Unexpected device: ({:#04x},{:#04x}), expected ({:#04x},{:#04x})", c,d, a,b),
+            Self::Config(e) => write!(f, "Bad ranging config: {}", e),
+            #[cfg(feature = "dci")]
+            Self::Dci(e) => write!(f, "Bad DCI buffer: {}", e),
+            #[cfg(uld_mixed)]
+            Self::NotSupported(v) => write!(f, "Not supported by the sensor: {:?}", v),
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self { Self::Config(e) }
+}
+
+#[cfg(feature = "dci")]
+impl From<DciError> for Error {
+    fn from(e: DciError) -> Self { Self::Dci(e) }
+}
+
+/*
+* ULD C API status codes ('VL_STATUS_...').
+*
+* Note: 'Other' values can be combinations (bitwise OR) of the others.
+*/
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UldStatus {
+    Timeout,            // 1
+    CorruptedFrame,     // 2
+    XtalkFailed,        // 4
+    McuError,           // 66 (0x42)
+    InvalidParam,       // 127 (0x7f); value given by the application
+    Error,              // 255; "major error (usually timeout in I2C)"
+    Other(u8),
+}
+
+impl From<u8> for UldStatus {
+    fn from(v: u8) -> Self {
+        match v {
+            1 => Self::Timeout,
+            2 => Self::CorruptedFrame,
+            4 => Self::XtalkFailed,
+            66 => Self::McuError,
+            127 => Self::InvalidParam,
+            ST_ERROR => Self::Error,
+            x => Self::Other(x),
+        }
+    }
+}
+
+impl Display for UldStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "timeout (1)"),
+            Self::CorruptedFrame => write!(f, "corrupted frame (2)"),
+            Self::XtalkFailed => write!(f, "Xtalk calibration failed (4)"),
+            Self::McuError => write!(f, "sensor MCU error (66)"),
+            Self::InvalidParam => write!(f, "invalid parameter (127)"),
+            Self::Error => write!(f, "major error (255)"),
+            Self::Other(x) => write!(f, "combined error ({})", x),
+        }
+    }
+}
+
+/*
+* Reasons a 'RangingConfig' is rejected. Values are the offending ones (or the limit, where noted).
+*/
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    IntegrationTime(u16),           // outside 2..=1000 ms
+    IntegrationWindow{ max_ms: u16 },   // integration time doesn't fit within the ranging period
+    Frequency(u8),                  // outside 1..=max, for the resolution
+    Sharpener(u8),                  // outside 1..=99 %
+    #[cfg(feature = "motion_indicator")]
+    MotionWindow(u16,u16),          // outside 400..=4000 mm, reversed, or wider than 1500 mm
+    #[cfg(feature = "detection_thresholds")]
+    NoThresholds,                   // an empty 'ThresholdConfig'
+    #[cfg(feature = "detection_thresholds")]
+    TooManyThresholds(usize),       // more than 64
+    #[cfg(feature = "detection_thresholds")]
+    ThresholdZone(u8,u8),           // '(row, column)' outside the resolution
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::IntegrationTime(v) => write!(f, "integration time out of range (2..=1000ms): {}", v),
+            Self::IntegrationWindow{ max_ms } => write!(f, "integration time exceeds the available window ({}ms)", max_ms),
+            Self::Frequency(v) => write!(f, "frequency out of range: {}", v),
+            Self::Sharpener(v) => write!(f, "sharpener out of range (1..=99): {}", v),
+            #[cfg(feature = "motion_indicator")]
+            Self::MotionWindow(a,b) => write!(f, "motion window out of range: {}..{}", a,b),
+            #[cfg(feature = "detection_thresholds")]
+            Self::NoThresholds => write!(f, "no detection thresholds given"),
+            #[cfg(feature = "detection_thresholds")]
+            Self::TooManyThresholds(n) => write!(f, "too many detection thresholds (max 64): {}", n),
+            #[cfg(feature = "detection_thresholds")]
+            Self::ThresholdZone(r,c) => write!(f, "detection threshold zone out of range: ({},{})", r,c),
+        }
+    }
+}
+
+/*
+* Reasons a DCI buffer is rejected (before calling the ULD C code). The C side swaps the data in
+* 4-byte groups, in place; a bad buffer would be cut short, or abort in 'VL_SwapBuffer'.
+*/
+#[cfg(feature = "dci")]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DciError {
+    Size(usize),                    // not a multiple of 4 (or doesn't fit 'u16')
+    Unaligned,                      // not 'u32' aligned; use 'dci::Aligned'
+    Overflow{ pos: u16, len: usize },   // 'new_data' at 'pos' doesn't fit within the buffer
+}
+
+#[cfg(feature = "dci")]
+impl Display for DciError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Size(n) => write!(f, "size not a multiple of 4: {}", n),
+            Self::Unaligned => write!(f, "not 'u32' aligned"),
+            Self::Overflow{ pos, len } => write!(f, "new data ({} bytes at {}) doesn't fit", len, pos),
+        }
+    }
+}
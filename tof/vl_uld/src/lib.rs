@@ -1,31 +1,72 @@
 #![no_std]
 #![allow(non_snake_case)]
 
+#[cfg(feature = "sim")]
+extern crate std;       // the simulated sensor is for host builds
+
+#[cfg(feature = "dci")]
+pub mod dci;
+mod error;
 mod platform;
 mod state_hp_idle;
+mod state_lp_idle;
 mod state_ranging;
 mod results_data;
+#[cfg(feature = "detection_thresholds")]
+mod thresholds;
+#[cfg(feature = "sim")]
+pub mod sim;
+mod uld;
 #[cfg(feature="vl53l5cx")]
-#[path = "../tmp/uld_raw5.rs"]
-mod uld_raw;
+#[cfg_attr(not(uld_host), path = "../tmp/uld_raw5.rs")]
+#[cfg_attr(uld_host, path = "../tmp/host/uld_raw5.rs")]     // host build; see 'build.rs'
+mod uld_raw5;
+#[cfg(feature="vl53l7cx")]
+#[cfg_attr(not(uld_host), path = "../tmp/uld_raw7.rs")]
+#[cfg_attr(uld_host, path = "../tmp/host/uld_raw7.rs")]
+mod uld_raw7;
 #[cfg(feature="vl53l8cx")]
-#[path = "../tmp/uld_raw8.rs"]
-mod uld_raw;
-pub mod units;
+#[cfg_attr(not(uld_host), path = "../tmp/uld_raw8.rs")]
+#[cfg_attr(uld_host, path = "../tmp/host/uld_raw8.rs")]
+mod uld_raw8;
+
+// Items that are the same for all variants (enums, status codes, DCI indices, 'VL_Platform'), from
+// one of the bindings. Per-variant items ('VL_Configuration', 'VL_ResultsData', the functions) are
+// reached via 'uld::with_uld!'.
+//
+// Note: In a mixed build, these come from the L8CX side (as does 'API_REVISION').
+//
+mod uld_raw {
+    #[cfg(feature="vl53l8cx")]
+    pub use crate::uld_raw8::*;
+    #[cfg(all(feature="vl53l5cx", not(feature="vl53l8cx")))]
+    pub use crate::uld_raw5::*;
+    #[cfg(feature="vl53l7cx")]
+    pub use crate::uld_raw7::*;
+}
+#[cfg(feature = "xtalk")]
+mod xtalk;
 
 #[cfg(feature = "defmt")]
-use defmt::{assert, debug, error, Format};
+use defmt::{debug, error};
 
-use core::{
-    ffi::CStr,
-    fmt::{Display, Formatter},
-    result::Result as CoreResult,
-};
+use core::ffi::CStr;
+
+#[cfg(feature = "detection_thresholds")]
+pub use thresholds::{Threshold, ThresholdCheck, ThresholdConfig, ThresholdMeas};
+#[cfg(feature = "xtalk")]
+pub use xtalk::XtalkData;
+#[cfg(feature = "dci")]
+pub use error::DciError;
 
 pub use {
-    platform::Platform,
+    error::{ConfigError, Error, UldStatus},
+    platform::{AsyncPlatform, Platform, PlatformError},
     results_data::ResultsData,
-    state_hp_idle::State_HP_Idle,
+    vl_common::{units, I2cAddr},
+    state_hp_idle::{State_HP_Idle, StartError},
+    state_lp_idle::{SleepError, State_LP_Idle, WakeError},
+    uld::Variant,
     state_ranging::{
         Mode,
         RangingConfig,
@@ -34,25 +75,16 @@ pub use {
     }
 };
 
-use crate::uld_raw::{
-    VL_Configuration,
-    vl_init,
-    API_REVISION as API_REVISION_r,   // &[u8] with terminating '\0'
-    ST_OK, ST_ERROR,
+use crate::{
+    uld::Uld,
+    uld_raw::{
+        API_REVISION as API_REVISION_r,   // &[u8] with terminating '\0'
+        ST_OK,
+    },
 };
 
 pub type Result<T> = core::result::Result<T,Error>;
 
-#[cfg_attr(feature = "defmt", derive(defmt::Format))]
-#[derive(core::fmt::Debug)]
-pub struct Error(pub u8);
-
-impl Display for Error {
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        write!(f, "ULD driver or hardware error ({})", self.0)
-    }
-}
-
 // Default after each power on; both L8 and L5CX
 pub const DEFAULT_I2C_ADDR: I2cAddr = I2cAddr::from_8bit(0x52);
 
@@ -68,15 +100,27 @@ pub const API_REVISION: &str = {
     }
 };
 
+// Rev id reported in 'Error::PingMismatch'. Mixed builds also take an L5CX (0x02); see 'Variant'.
 const CORRECT_REV_ID: u8 = if cfg!(feature = "vl53l8cx") { 0x0c } else { 0x02 };
 
 /*
-* Adds a method to the ULD C API struct.
+* Field of view (horizontal, vertical) and diagonal, in degrees. From the datasheets.
+*
+* Mixed builds: see '.variant().fov_deg()' of the sensor.
+*/
+#[cfg(not(uld_mixed))]
+pub const FOV_DEG: (u8,u8) = uld::ONLY.fov_deg();
+#[cfg(not(uld_mixed))]
+pub const FOV_DIAGONAL_DEG: u8 = uld::ONLY.fov_diagonal_deg();
+
+/*
+* Adds a method to the ULD C API struct (of each variant built in).
 *
 * Note: Since the C-side struct has quite a lot of internal "bookkeeping" fields, we don't expose
 *       this directly to Rust customers, but wrap it.
 */
-impl VL_Configuration {
+macro_rules! impl_init_with { ($raw:ident) => {
+impl crate::$raw::VL_Configuration {
     /** @brief Returns a default 'VL_Configuration' struct, spiced with the application
        * provided 'Platform'-derived state (opaque to us, except for its size).
        *
@@ -107,50 +151,24 @@ impl VL_Configuration {
        *   - two bytes updated at sensor's DCI memory at '0x0e108' ('VL_GLARE_FILTER'):
        *       {0x01, 0x01}
     */
-    fn init_with(mut p: impl Platform) -> Result<Self> {
+    pub(crate) fn init_with(p: impl Platform + 'static) -> Result<Self> {
         use core::{
             mem::MaybeUninit,
             ptr::addr_of_mut
         };
 
         #[allow(unused_unsafe)]
-        let ret: Result<VL_Configuration> = unsafe {
-            let mut uninit = MaybeUninit::<VL_Configuration>::uninit();
+        let ret: Result<Self> = unsafe {
+            let mut uninit = MaybeUninit::<Self>::uninit();
             let up = uninit.as_mut_ptr();
 
-            // Get a '&mut dyn Platform' reference to 'p'. Converting '*c_void' (once we come to
-            // need the 'Platform') to a *templated* 'P' is beyond the author's imagination and
-            // also unnecessary. '&mut dyn Platform' is just within doable!
+            // Move 'p' into the '.platform' slot; ULD C 'vl.._init()' will need it, to access the
+            // I2C bus (below). Size and alignment get checked at compile time.
             //
-            // Nice part of using '&mut dyn Platform' is also that the size and alignment requirements
-            // (48 and 8 bytes), remain constant for the C side.
+            // Note: The slot ('platform.h') is the same for all variants; we see it via 'uld_raw'.
             //
-            // NOTE! Until 18-Aug-25, the Rust side size was 20. Not sure what changed it to 48.
-            //
-            let dd: &mut dyn Platform = &mut p;
-            let pp = addr_of_mut!((*up).platform);
-
-            // Check size and alignment
-            {
-                // Note: It's difficult (it seems) to make the C side both 8-aligned and 20-wide.
-                //      The compiler makes the struct 24-wide, in that case. So we allow the gap.
-                let sz_c = size_of_val(&(*up).platform);
-                let sz_rust = size_of_val(dd);
-                assert!(sz_c >= sz_rust, "Tunnel C side isn't wide enough: {} < {}", sz_c, sz_rust);   // edit 'platform.h' to adjust
-
-                let al_rust = align_of_val(dd);
-                assert!( (pp as usize)%al_rust == 0 ||false, "bad alignment on C side (needs {})", al_rust );
-            }
-
-            // Make a bitwise copy of 'dd' in 'uninit.platform'; ULD C 'vl.._init()' will need it,
-            // to access the I2C bus (below).
-            {
-                // We did try various 'replace' and 'transmute', but this turned out to be the
-                // working solution.
-                //
-                *(pp as *mut &mut dyn Platform) = dd;
-                core::mem::forget(p);   // ESSENTIAL! no drop for 'p'; the handle now follows 'Platform' lifespan (likely static)
-            }
+            let pp: *mut uld_raw::VL_Platform = addr_of_mut!((*up).platform).cast();
+            platform::install(pp, p);
 
             // Initialize those fields we know C API won't touch (just in case)
             addr_of_mut!((*up).streamcount).write(u8::MAX);
@@ -160,20 +178,32 @@ impl VL_Configuration {
             //
             // Note: Already this will call the platform methods (via the tunnel).
             //
-            match vl_init(up) {
+            match crate::$raw::vl_init(up) {
                 ST_OK => Ok(uninit.assume_init()),  // we guarantee it's now initialized
-                e => Err(Error(e))
+                e => Err(match platform::take_pending(pp) {
+                    Some(pe) => Error::Platform(pe),
+                    None => Error::uld(e)
+                })
             }
         };
         ret
     }
 }
+}}
+
+#[cfg(feature="vl53l5cx")]
+impl_init_with!(uld_raw5);
+#[cfg(feature="vl53l7cx")]
+impl_init_with!(uld_raw7);
+#[cfg(feature="vl53l8cx")]
+impl_init_with!(uld_raw8);
 
 /**
 * @brief Beginning of preparing access to a single VL53{L5CX|L8} sensor.
 */
 pub struct VL53<P: Platform + 'static> {
-    p: P
+    p: P,
+    variant: Variant,   // by the rev id; picks the ULD C driver (mixed builds)
 }
 
 impl<P: Platform + 'static> VL53<P> {
@@ -182,45 +212,53 @@ impl<P: Platform + 'static> VL53<P> {
     * a suitable sensor out there.
     */
     pub fn new_with_ping(/*move*/ mut p: P) -> Result<Self> {
-        match Self::ping(&mut p) {
-            Err(_) => Err(Error(ST_ERROR)),
-            Ok(()) => Ok(Self{ p })
-        }
+        let variant = Self::ping(&mut p)?;
+        Ok(Self{ p, variant })
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
     }
 
     pub fn init(self) -> Result<State_HP_Idle> {
-        let uld = VL_Configuration::init_with(/*move*/ self.p)?;
+        let uld = Uld::init_with(self.variant, /*move*/ self.p)?;
 
         Ok( State_HP_Idle::new(uld) )
     }
 
-    fn ping(p: &mut P) -> CoreResult<(),()> {
+    fn ping(p: &mut P) -> Result<Variant> {
+        let found = vl_ping(p).map_err(|e| Error::Platform(e.into()))?;
+
         #[cfg_attr(not(feature="defmt"), allow(unused_variables))]
+        match found {
+            (a@ 0xf0, b) => match Variant::from_rev_id(b) {
+                Some(v) => {
+                    #[cfg(feature="defmt")]
+                    debug!("Ping succeeded: {=u8:#04x},{=u8:#04x} ({})", a,b, v);
+                    return Ok(v)
+                },
+                None => {
+                    #[cfg(feature="defmt")]
+                    {
+                        #[cfg(all(feature="vl53l8cx", not(uld_mixed)))]
+                        const EXPECTED: &str = "L8CX (0x0c)";
+                        #[cfg(all(feature="vl53l5cx", not(uld_mixed)))]
+                        const EXPECTED: &str = "L5CX (0x02)";
+                        #[cfg(feature="vl53l7cx")]
+                        const EXPECTED: &str = "L7CX (0x02)";
+                        #[cfg(uld_mixed)]
+                        const EXPECTED: &str = "L5CX (0x02) or L8CX (0x0c)";
 
-        match vl_ping(p)? {
-            (a@ 0xf0, b@ CORRECT_REV_ID) => {
-                #[cfg(feature="defmt")]
-                debug!("Ping succeeded: {=u8:#04x},{=u8:#04x}", a,b);
-                Ok(())
-            },
-            #[cfg(feature="vl53l8cx")]
-            (0xf0, b) => {
-                #[cfg(feature="defmt")]
-                error!("Expected L8CX (0x0c), found rev id: {:#04x}", b);
-                Err(())
-            },
-            #[cfg(feature="vl53l5cx")]
-            (0xf0, b) => {
-                #[cfg(feature="defmt")]
-                error!("Expected L5CX (0x02), found rev id: {:#04x}", b);
-                Err(())
+                        error!("Expected {}, found rev id: {:#04x}", EXPECTED, b);
+                    }
+                }
             },
             t => {
                 #[cfg(feature="defmt")]
                 error!("Unexpected '(device id, rev id)': {:#04x}", t);
-                Err(())
             }
         }
+        Err(Error::PingMismatch{ expected: (0xf0, CORRECT_REV_ID), found })
     }
 }
 
@@ -233,9 +271,10 @@ impl<P: Platform + 'static> VL53<P> {
 * is supposed to be functioning also before the firmware and parameters initialization.
 *
 * Note:
-*   - Vendor's ULD C driver expects '(0xf0, 0x0c)' (L8CX) or '(0xf0, 0x02)' (L5CX).
+*   - Vendor's ULD C driver expects '(0xf0, 0x0c)' (L8CX) or '(0xf0, 0x02)' (L5CX). The rev id also
+*     picks the variant, in mixed builds.
 */
-fn vl_ping<P : Platform>(pl: &mut P) -> CoreResult<(u8,u8),()> {
+fn vl_ping<P : Platform>(pl: &mut P) -> core::result::Result<(u8,u8),P::Error> {
     let mut buf = [u8::MAX;2];
 
     pl.wr_bytes(0x7fff, &[0x00])?;
@@ -244,36 +283,3 @@ fn vl_ping<P : Platform>(pl: &mut P) -> CoreResult<(u8,u8),()> {
 
     Ok( (buf[0], buf[1]) )
 }
-
-/*
-* Wrapper to eliminate 8-bit vs. 7-bit I2C address misunderstandings.
-*
-* Note: Not using 'esp-hal' 'i2c::master::I2cAddress' to keep the door ever so slightly ajar for
-*       other MCU families. If someone wants to do the work.
-*/
-#[derive(Copy,Clone,Eq,PartialEq)]
-pub struct I2cAddr(u8);     // stored as 7-bit (internal detail)
-
-impl I2cAddr {
-    pub const fn from_8bit(v: u8) -> Self {
-        //assert!(v % 2 == 0 ||false, "8-bit I2C address is expected to be even");      // cannot use 'assert!' in 'const' fn's, since 'defmt' 1.0
-        //  tbd. report this to 'defmt-macros' GitHub; that 'assert!' used to work in 'const fn' context prior to 1.0
-        //      and isn't mentioned in any changelogs that it no longer should not.
-        Self(v >> 1)
-    }
-    pub fn from_7bit(v: u8) -> Self {
-        assert!(v < 0x80, "not 7-bit");
-        Self(v)
-    }
-    pub const fn as_7bit(&self) -> u8 { self.0 }      // used by platform code (needs to be 'pub')
-    //fn as_8bit(&self) -> u8 { self.0 << 1 }
-}
-
-#[cfg(feature = "defmt")]
-impl Format for I2cAddr {
-    fn format(&self, fmt: defmt::Formatter) {
-        // 'esp-hal' (as most of the world) uses 7-bit I2C addresses, but the vendor uses 8-bit.
-        // It IS confusing, but don't want to go full 8-bit. Treating vendor as the exception!
-        defmt::write!(fmt, "{=u8:#04x}_u7", self.as_7bit());
-    }
-}
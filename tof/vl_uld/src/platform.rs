@@ -8,35 +8,140 @@
 use defmt::{trace, warn};
 
 use core::{
-    ffi::c_void,
+    mem::{align_of, size_of},
+    ptr,
     slice,
     result::Result as CoreResult,
 };
 
-use crate::I2cAddr;
+use crate::{
+    Error,
+    I2cAddr,
+};
+pub use vl_common::{AsyncPlatform, Platform, PlatformError};     // shared with the other sensor crates
 use crate::uld_raw::{
     ST_OK,
     ST_ERROR as ST_ERR,
-    VL_Platform
+    VL_Platform     // same layout for all variants ('platform.h')
 };
 
-/**
-* @brief App provides, to talk to the I2C and do blocking delays; provides a mechanism to inform
-*       the platform about an I2C address change.
+/*
+* The tunnel, from the ULD C code's point of view.
+*
+* The 'Platform' is stored BY VALUE in the 'VL_Platform' slot of 'VL_Configuration' (which the
+* C side treats as opaque bytes). This way, it moves along with the configuration struct, whichever
+* Rust state owns it.
+*
+* The C side only gives us a '*mut VL_Platform'; to get back to the generic 'P', the first field of
+* the slot is a (monomorphized) function that knows it.
+*
+* A platform error is kept in the slot until the Rust side, seeing a failed ULD status, picks it
+* up. Only the first error (since last pick-up) is kept; the rest are likely consequences.
+*
+* 'prefetched' marks a read ('(index, size)') that the Rust side has already done (async), into
+* the very buffer the C code is about to read into. The next read is served from it - if it
+* matches - and the mark is cleared either way.
 */
-pub trait Platform {
-    // provided by the app
-    //
-    fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> CoreResult<(),()>;
-    fn wr_bytes(&mut self, index: u16, vs: &[u8]) -> CoreResult<(),()>;
+pub(crate) trait Tunnel {
+    fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> CoreResult<(),PlatformError>;
+    fn wr_bytes(&mut self, index: u16, vs: &[u8]) -> CoreResult<(),PlatformError>;
     fn delay_ms(&mut self, ms: u32);
-
-    // This is our addition (vendor API struggles with the concept). Once we have changed the I2C
-    // address the device identifies with, inform the 'Platform' struct about it.
-    //
     fn addr_changed(&mut self, addr: &I2cAddr);
 }
 
+impl<P: Platform> Tunnel for P {
+    fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> CoreResult<(),PlatformError> {
+        Platform::rd_bytes(self, index, buf).map_err(Into::into)
+    }
+    fn wr_bytes(&mut self, index: u16, vs: &[u8]) -> CoreResult<(),PlatformError> {
+        Platform::wr_bytes(self, index, vs).map_err(Into::into)
+    }
+    fn delay_ms(&mut self, ms: u32) { Platform::delay_ms(self, ms) }
+    fn addr_changed(&mut self, addr: &I2cAddr) { Platform::addr_changed(self, addr) }
+}
+
+#[repr(C)]
+struct Slot<P> {
+    as_dyn: unsafe fn(*mut VL_Platform) -> *mut dyn Tunnel,   // must be first
+    pending: Option<PlatformError>,
+    prefetched: Option<(u16,u32)>,
+    p: P,
+}
+
+#[repr(C)]
+struct SlotHead {       // the non-generic beginning of 'Slot<P>'
+    as_dyn: unsafe fn(*mut VL_Platform) -> *mut dyn Tunnel,
+    pending: Option<PlatformError>,
+    prefetched: Option<(u16,u32)>,
+}
+
+unsafe fn as_dyn<P: Platform + 'static>(pt: *mut VL_Platform) -> *mut dyn Tunnel {
+    let slot = pt as *mut Slot<P>;
+    unsafe { ptr::addr_of_mut!((*slot).p) as *mut dyn Tunnel }
+}
+
+/*
+* Move 'p' into the (uninitialized) slot.
+*
+* Note: There's no 'Drop' for 'VL_Configuration', so 'p' never gets dropped.
+*/
+pub(crate) fn install<P: Platform + 'static>(pt: *mut VL_Platform, p: P) {
+    const {
+        assert!(size_of::<Slot<P>>() <= size_of::<VL_Platform>(), "'Platform' too big for the C side slot; edit 'platform.h'");
+        assert!(align_of::<Slot<P>>() <= align_of::<VL_Platform>(), "'Platform' alignment above that of the C side slot");
+    }
+
+    unsafe {
+        ptr::write(pt as *mut Slot<P>, Slot{ as_dyn: as_dyn::<P>, pending: None, prefetched: None, p });
+    }
+}
+
+/*
+* Pick up the platform error (if any) left by the tunnel.
+*/
+pub(crate) fn take_pending(pt: *mut VL_Platform) -> Option<PlatformError> {
+    unsafe { (*(pt as *mut SlotHead)).pending.take() }
+}
+
+fn set_pending(pt: *mut VL_Platform, e: PlatformError) {
+    let head = unsafe { &mut *(pt as *mut SlotHead) };
+    if head.pending.is_none() {
+        head.pending = Some(e);
+    }
+}
+
+/*
+* Mark that the next '(index, size)' read has already been done, by the caller.
+*/
+pub(crate) fn set_prefetched(pt: *mut VL_Platform, index: u16, size: u32) {
+    unsafe { (*(pt as *mut SlotHead)).prefetched = Some((index, size)); }
+}
+
+fn take_prefetched(pt: *mut VL_Platform) -> Option<(u16,u32)> {
+    unsafe { (*(pt as *mut SlotHead)).prefetched.take() }
+}
+
+macro_rules! impl_error_from { ($raw:ident) => {
+impl crate::$raw::VL_Configuration {
+    /*
+    * Turn a failed ULD status into an 'Error'. If the tunnel saw an I2C error, that's the cause.
+    */
+    pub(crate) fn error_from(&mut self, st: u8) -> Error {
+        match take_pending(ptr::addr_of_mut!(self.platform).cast()) {
+            Some(e) => Error::Platform(e),
+            None => Error::uld(st)
+        }
+    }
+}
+}}
+
+#[cfg(feature = "vl53l5cx")]
+impl_error_from!(uld_raw5);
+#[cfg(feature = "vl53l7cx")]
+impl_error_from!(uld_raw7);
+#[cfg(feature = "vl53l8cx")]
+impl_error_from!(uld_raw8);
+
 /*
 * Raw part of interfacing.
 *
@@ -93,16 +198,15 @@ pub extern "C" fn VL_RdMulti(
     p_values: *mut u8,
     size: u32   // should be 'size_t'
 ) -> u8 {
-    with(pt, |p| {
-        let r = match p.rd_bytes(index, unsafe { slice::from_raw_parts_mut(p_values, size as usize) } ) {
-            Ok(()) => ST_OK,
-            Err(_) => ST_ERR
-        };
-
-        // There should be 1.3ms between transmissions, by the VL spec. (see 'tBUF', p.15)
-        p.delay_ms(1);    // 1300us
-        r
-    })
+    // Already read (async) into 'p_values'?
+    if take_prefetched(pt) == Some((index, size)) {
+        return ST_OK;
+    }
+
+    let r = with(pt, |p|
+        p.rd_bytes(index, unsafe { slice::from_raw_parts_mut(p_values, size as usize) } )
+    );
+    status(pt, r)
 }
 
 /// @brief write multiples bytes
@@ -118,16 +222,10 @@ pub extern "C" fn VL_WrMulti(
     p_values: *mut u8,  // *u8 (const)
     size: u32   // actual values fit 16 bits; 'size_t'
 ) -> u8 {
-    with(pt, |p| {
-        let r = match p.wr_bytes(index, unsafe { slice::from_raw_parts(p_values, size as usize) } ) {
-            Ok(()) => ST_OK,
-            Err(_) => ST_ERR
-        };
-
-        // There should be 1.3ms between transmissions, by the VL spec. (see 'tBUF', p.15)
-        p.delay_ms(1);    // 1300us
-        r
-    })
+    let r = with(pt, |p|
+        p.wr_bytes(index, unsafe { slice::from_raw_parts(p_values, size as usize) } )
+    );
+    status(pt, r)
 }
 
 // NOTE: Vendor docs don't really describe what the "4-byte grouping" means, but their 'protocol.c'
@@ -166,20 +264,26 @@ pub extern "C" fn VL_WaitMs(pt: *mut VL_Platform, time_ms: u32) -> u8 {
     })
 }
 
-pub(crate)  // open for 'set_i2c_address()' so that the I2C address can be changed, on the fly!!!
-fn with<T, F: Fn(&mut dyn Platform) -> T>(pt: *mut VL_Platform, f: F) -> T {
+fn status(pt: *mut VL_Platform, r: CoreResult<(),PlatformError>) -> u8 {
+    match r {
+        Ok(()) => ST_OK,
+        Err(e) => {
+            #[cfg(feature = "defmt")]
+            warn!("Platform error: {}", e);
 
-    let x: &mut dyn Platform = {    // re-interpret what's in '*pt' as '&mut dyn Platform'
-        let pt: *mut &mut dyn Platform = pt as *mut c_void as _;
-        unsafe{ *pt }
-    };
+            set_pending(pt, e);
+            ST_ERR
+        }
+    }
+}
+
+pub(crate)  // open for 'set_i2c_address()' so that the I2C address can be changed, on the fly!!!
+fn with<T, F: FnOnce(&mut dyn Tunnel) -> T>(pt: *mut VL_Platform, f: F) -> T {
 
-    /*** Something we didn't try:
-    let pt = unsafe {
-        core::ptr::NonNull::new_unchecked(pt).cast::<&mut dyn Platform>().as_ptr()
+    let x: &mut dyn Tunnel = unsafe {   // the slot knows its 'P'
+        let as_dyn = (*(pt as *mut SlotHead)).as_dyn;
+        &mut *as_dyn(pt)
     };
-    unsafe { *pt }
-    ***/
 
     f(x)
 }
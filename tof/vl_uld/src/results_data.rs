@@ -17,9 +17,6 @@
 #[cfg(feature = "defmt")]
 #[allow(unused_imports)]
 use defmt::{assert, debug, panic, warn};
-use crate::uld_raw::{
-    VL_ResultsData,
-};
 use crate::units::TempC;
 
 // Note: We could also take in 'TARGETS_PER_ZONE' from the ULD C API wrapper.
@@ -29,6 +26,62 @@ const TARGETS: usize =
     else if cfg!(feature = "targets_per_zone_2") { 2 }
     else { 1 };
 
+/*
+* The ULD C API results struct ('VL_ResultsData'), of any variant. The fields are the same, but
+* each variant's bindings have a type of their own.
+*/
+pub(crate) trait RawResults {
+    // L8 provides negative '.distance_mm' values, at times; L5CX keeps them >= 0.
+    const NEGATIVE_DISTANCES: bool;
+
+    fn silicon_temp_degc(&self) -> i8;
+    #[cfg(feature = "ambient_per_spad")]
+    fn ambient_per_spad(&self) -> &[u32];
+    #[cfg(feature = "nb_spads_enabled")]
+    fn nb_spads_enabled(&self) -> &[u32];
+    #[cfg(feature = "motion_indicator")]
+    fn motion(&self) -> &[u32];
+    fn nb_target_detected(&self) -> &[u8];
+    fn distance_mm(&self) -> &[i16];
+    fn target_status(&self) -> &[u8];
+    #[cfg(feature = "range_sigma_mm")]
+    fn range_sigma_mm(&self) -> &[u16];
+    #[cfg(feature = "reflectance_percent")]
+    fn reflectance(&self) -> &[u8];
+    #[cfg(feature = "signal_per_spad")]
+    fn signal_per_spad(&self) -> &[u32];
+}
+
+macro_rules! impl_raw_results { ($raw:ident, $negative_distances:expr) => {
+impl RawResults for crate::$raw::VL_ResultsData {
+    const NEGATIVE_DISTANCES: bool = $negative_distances;
+
+    fn silicon_temp_degc(&self) -> i8 { self.silicon_temp_degc }
+    #[cfg(feature = "ambient_per_spad")]
+    fn ambient_per_spad(&self) -> &[u32] { &self.ambient_per_spad }
+    #[cfg(feature = "nb_spads_enabled")]
+    fn nb_spads_enabled(&self) -> &[u32] { &self.nb_spads_enabled }
+    #[cfg(feature = "motion_indicator")]
+    fn motion(&self) -> &[u32] { &self.motion_indicator.motion }
+    fn nb_target_detected(&self) -> &[u8] { &self.nb_target_detected }
+    fn distance_mm(&self) -> &[i16] { &self.distance_mm }
+    fn target_status(&self) -> &[u8] { &self.target_status }
+    #[cfg(feature = "range_sigma_mm")]
+    fn range_sigma_mm(&self) -> &[u16] { &self.range_sigma_mm }
+    #[cfg(feature = "reflectance_percent")]
+    fn reflectance(&self) -> &[u8] { &self.reflectance }
+    #[cfg(feature = "signal_per_spad")]
+    fn signal_per_spad(&self) -> &[u32] { &self.signal_per_spad }
+}
+}}
+
+#[cfg(feature = "vl53l5cx")]
+impl_raw_results!(uld_raw5, false);
+#[cfg(feature = "vl53l7cx")]
+impl_raw_results!(uld_raw7, true);      // not checked
+#[cfg(feature = "vl53l8cx")]
+impl_raw_results!(uld_raw8, true);
+
 /*
 * Results data, in matrix format.
 *
@@ -50,6 +103,8 @@ pub struct ResultsData<const DIM: usize> {      // DIM: 4,8
     pub ambient_per_spad: [[u32; DIM]; DIM],
     #[cfg(feature = "nb_spads_enabled")]
     pub spads_enabled: [[u32; DIM]; DIM],
+    #[cfg(feature = "motion_indicator")]
+    pub motion: [[u32; DIM]; DIM],      // motion intensity; 0 = no motion. For 8x8, zones come in pairs (see below).
 
     // Actual results: DIMxDIMxTARGETS
     pub meas: [[[Meas; DIM]; DIM]; TARGETS],
@@ -64,7 +119,7 @@ pub struct ResultsData<const DIM: usize> {      // DIM: 4,8
 
 impl<const DIM: usize> ResultsData<DIM> {
 
-    pub(crate) fn from(raw_results: &VL_ResultsData) -> (Self,TempC) {
+    pub(crate) fn from(raw_results: &impl RawResults) -> (Self,TempC) {
         use core::mem::MaybeUninit;
 
         let mut x: Self = {
@@ -76,7 +131,7 @@ impl<const DIM: usize> ResultsData<DIM> {
         (x, tempC)
     }
 
-    fn feed(&mut self, rr: &VL_ResultsData) -> TempC {
+    fn feed<R: RawResults>(&mut self, rr: &R) -> TempC {
         use core::convert::identity;
 
         // helpers
@@ -141,9 +196,23 @@ impl<const DIM: usize> ResultsData<DIM> {
         // Metadata: DIMxDIM (just once)
         //
         #[cfg(feature = "ambient_per_spad")]
-        into_matrix(&rr.ambient_per_spad, &mut self.ambient_per_spad);
+        into_matrix(rr.ambient_per_spad(), &mut self.ambient_per_spad);
         #[cfg(feature = "nb_spads_enabled")]
-        into_matrix(&rr.nb_spads_enabled, &mut self.spads_enabled);
+        into_matrix(rr.nb_spads_enabled(), &mut self.spads_enabled);
+
+        // Motion indicator reports per "aggregate" (max. 32), not per zone. The mapping is from the
+        // vendor plugin ('..._motion_indicator_set_resolution()'):
+        //  - 4x4: one zone per aggregate
+        //  - 8x8: two horizontally adjacent zones per aggregate; both get the same value
+        //
+        #[cfg(feature = "motion_indicator")]
+        for r in 0..DIM {
+            for c in 0..DIM {
+                let i = r*DIM + c;
+                let agg = if DIM == 4 { i } else { (i%16)/2 + 8*(i/16) };
+                self.motion[r][c] = rr.motion()[agg];
+            }
+        }
 
         // Results: DIMxDIMxTARGETS
         //
@@ -155,9 +224,9 @@ impl<const DIM: usize> ResultsData<DIM> {
             {
                 use core::mem::MaybeUninit;
 
-                let r_nb = &rr.nb_target_detected[..DIM * DIM];
-                let r_dist = &rr.distance_mm[..DIM * DIM * TARGETS];
-                let r_ts = &rr.target_status[..DIM * DIM * TARGETS];
+                let r_nb = &rr.nb_target_detected()[..DIM * DIM];
+                let r_dist = &rr.distance_mm()[..DIM * DIM * TARGETS];
+                let r_ts = &rr.target_status()[..DIM * DIM * TARGETS];
 
                 // Output data that's the same for all targets.
                 if i==0 {
@@ -192,19 +261,20 @@ impl<const DIM: usize> ResultsData<DIM> {
             // into one; intended to make application level data use trivial (we help them select
             // what data is valid).
             //
-            into_matrix_map_o_pos(&rr.distance_mm, i, &mut self.meas[i],
+            into_matrix_map_o_pos(rr.distance_mm(), i, &mut self.meas[i],
             |v: i16, pos: usize| -> Meas {
                 // L5CX: keeps the values >= 0.
                 // L8: also provides negative values, at times.
                 //
-                #[cfg(feature = "vl53l5cx")]
-                assert!(v >= 0, "Unexpected '.distance_mm' value: {} < 0", v);
+                if !R::NEGATIVE_DISTANCES {
+                    assert!(v >= 0, "Unexpected '.distance_mm' value: {} < 0", v);
+                }
 
                 let i2: usize = pos*TARGETS + i;    // index for per-target data ('target_status')
                 let i3: usize = pos + i;            // index for meta data
 
-                let target_status = rr.target_status[i2];
-                let detected = rr.nb_target_detected[i3];
+                let target_status = rr.target_status()[i2];
+                let detected = rr.nb_target_detected()[i3];
 
                 let /*mut*/ ret: Meas;
 
@@ -252,12 +322,12 @@ impl<const DIM: usize> ResultsData<DIM> {
                 }
             });
             #[cfg(feature = "range_sigma_mm")]
-            into_matrix_o(&rr.range_sigma_mm, i, &mut self.range_sigma_mm[i]);
+            into_matrix_o(rr.range_sigma_mm(), i, &mut self.range_sigma_mm[i]);
 
             #[cfg(feature = "reflectance_percent")]
-            into_matrix_o(&rr.reflectance, i, &mut self.reflectance[i]);
+            into_matrix_o(rr.reflectance(), i, &mut self.reflectance[i]);
             #[cfg(feature = "signal_per_spad")]
-            into_matrix_o(&rr.signal_per_spad, i, &mut self.signal_per_spad[i]);
+            into_matrix_o(rr.signal_per_spad(), i, &mut self.signal_per_spad[i]);
         }
 
         // Check out multi-target results. They may contain valid (or semi-valid) measurements that,
@@ -293,7 +363,7 @@ impl<const DIM: usize> ResultsData<DIM> {
             }
         }
 
-        TempC(rr.silicon_temp_degc)
+        TempC(rr.silicon_temp_degc())
     }
 }
 
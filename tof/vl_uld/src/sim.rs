@@ -0,0 +1,402 @@
+/*
+* Simulated sensor, for running the ULD C code on the host ('cargo test'), without hardware.
+*
+* 'SimSensor' plays the board; '.platform()' gives a 'Platform' talking to it. The test keeps the
+* 'SimSensor' around, to steer it and to peek at its state.
+*
+* What's emulated - enough for 'init', 'set_i2c_address', 'start_ranging', 'get_data' and 'stop':
+*   - page select ('0x7fff'); device and revision ids (page 0: '0x00', '0x01')
+*   - boot and MCU status ("GO2"; page 0: '0x06', '0x07'); firmware access (page 1: '0x21')
+*   - firmware upload: pages 9..11 need to be written, or the first UI command fails (MCU error)
+*   - the UI command area (page 2: '0x2c00..=0x2fff'): DCI writes and reads, starting a ranging
+*   - data ready, and canned results frames (each zone: one target, at the same distance)
+*   - I2C address change (page 0: '0x04'); accesses to another address are not acknowledged
+*
+* Other registers are plain memory: what's written reads back; the rest reads as zeros.
+*
+*   Usage:
+*   <<
+*       let sensor = SimSensor::new();
+*       let vl = VL53::new_with_ping(sensor.platform())?.init()?;
+*   <<
+*
+* Note: This is not a firmware emulation. It's modelled after what the ULD 2.0 C code expects; e.g.
+*       the ranging config (frequency, integration time) doesn't affect the frames.
+*
+* tbd. Only run against the L5CX ULD so far. The L8CX has more to it (e.g. a firmware checksum).
+*       Mixed builds: the board answers as an L5CX.
+*/
+#![cfg(feature = "sim")]
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    CORRECT_REV_ID,
+    DEFAULT_I2C_ADDR,
+    I2cAddr,
+    Platform,
+    PlatformError,
+};
+use crate::uld_raw::{
+    DCI_OUTPUT_CONFIG,
+    DCI_OUTPUT_ENABLES,
+    DCI_OUTPUT_LIST,
+    DISTANCE_IDX,
+    METADATA_IDX,
+    NB_TARGET_DETECTED_IDX,
+    TARGET_STATUS_IDX,
+};
+
+// Mixed builds: be an L5CX (see above)
+const REV_ID: u8 = if cfg!(uld_mixed) { 0x02 } else { CORRECT_REV_ID };
+
+const UI_START: u16 = 0x2c00;       // 'VL_UI_CMD_STATUS'; the command area runs till 'VL_UI_CMD_END'
+const UI_END: u16 = 0x2fff;         // commands are written so that they end here
+
+const START_CMD: [u8;4] = [0x00, 0x03, 0x00, 0x00];     // 'vl_start_ranging()'
+const DCI_READ_FOOTER: [u8;8] = [0x00, 0x00, 0x00, 0x0f, 0x00, 0x02, 0x00, 0x08];
+
+// DCI index that 'vl_start_ranging()' reads back, to check the firmware agrees on the frame size.
+const DCI_UI_RANGE_DATA: u16 = 0x5440;
+
+/*
+* The simulated board. Clones refer to the same board.
+*/
+#[derive(Clone)]
+pub struct SimSensor(Rc<RefCell<Sim>>);
+
+impl SimSensor {
+    pub fn new() -> Self {
+        Self(Rc::new(RefCell::new(Sim{
+            addr: DEFAULT_I2C_ADDR,
+            page: 0,
+            regs: HashMap::new(),
+            fw_pages: 0,
+            fw_rejected: false,
+            ui: vec![0; (UI_END - UI_START) as usize + 1],
+            dci: HashMap::new(),
+            ranging: None,
+            stream: 0,
+            frame: vec!(),
+            distance_mm: 1000,
+            temp_degc: 25,
+        })))
+    }
+
+    // A 'Platform' to talk to the board. Like a freshly powered board, it starts at the default
+    // I2C address.
+    //
+    pub fn platform(&self) -> SimPlatform {
+        SimPlatform{ sim: self.0.clone(), addr: DEFAULT_I2C_ADDR }
+    }
+
+    // What the coming frames carry: one target per zone, at 'distance_mm'.
+    //
+    pub fn set_frame(&self, distance_mm: u16, temp_degc: i8) {
+        assert!(distance_mm < 0x2000, "distance doesn't fit the frame (1/4 mm, 'i16')");
+
+        let mut sim = self.0.borrow_mut();
+        sim.distance_mm = distance_mm;
+        sim.temp_degc = temp_degc;
+    }
+
+    // Don't accept the firmware upload, as if it had been corrupted on the way.
+    //
+    pub fn reject_firmware(&self) {
+        self.0.borrow_mut().fw_rejected = true;
+    }
+
+    pub fn i2c_addr(&self) -> I2cAddr {
+        self.0.borrow().addr
+    }
+
+    pub fn is_ranging(&self) -> bool {
+        self.0.borrow().ranging.is_some()
+    }
+}
+
+impl Default for SimSensor {
+    fn default() -> Self { Self::new() }
+}
+
+/*
+* The 'Platform', for a 'SimSensor'. No time passes in delays; the board is always ready.
+*/
+pub struct SimPlatform {
+    sim: Rc<RefCell<Sim>>,
+    addr: I2cAddr,      // where the platform thinks the board is
+}
+
+impl Platform for SimPlatform {
+    type Error = PlatformError;
+
+    fn rd_bytes(&mut self, index: u16, buf: &mut [u8]) -> Result<(),PlatformError> {
+        let mut sim = self.sim.borrow_mut();
+        sim.check(&self.addr)?;
+        sim.rd(index, buf);
+        Ok(())
+    }
+
+    fn wr_bytes(&mut self, index: u16, vs: &[u8]) -> Result<(),PlatformError> {
+        let mut sim = self.sim.borrow_mut();
+        sim.check(&self.addr)?;
+        sim.wr(index, vs);
+        Ok(())
+    }
+
+    fn delay_ms(&mut self, _ms: u32) {}
+
+    fn addr_changed(&mut self, addr: &I2cAddr) {
+        self.addr = *addr;
+    }
+}
+
+struct Sim {
+    addr: I2cAddr,
+    page: u8,
+    regs: HashMap<(u8,u16),u8>,     // pages other than 2 (and the firmware ones)
+    fw_pages: u8,                   // bits for pages 9..11, once written
+    fw_rejected: bool,
+    ui: Vec<u8>,                    // page 2: 'UI_START..=UI_END'
+    dci: HashMap<u16,Vec<u8>>,      // DCI memory, by index (in host byte order, as the ULD C code has it)
+    ranging: Option<Layout>,
+    stream: u8,
+    frame: Vec<u8>,                 // current results frame, as on the wire
+    distance_mm: u16,
+    temp_degc: i8,
+}
+
+/*
+* Results frame, as asked for by 'vl_start_ranging()' (via DCI).
+*/
+struct Layout {
+    size: usize,        // 'data_read_size'
+    blocks: Vec<u32>,   // block headers ('type:4, size:12, idx:16'), in frame order
+}
+
+impl Sim {
+    fn check(&self, addr: &I2cAddr) -> Result<(),PlatformError> {
+        if *addr == self.addr { Ok(()) } else { Err(PlatformError::NoAcknowledge) }
+    }
+
+    fn fw_ok(&self) -> bool {
+        self.fw_pages == 0b111 && !self.fw_rejected
+    }
+
+    fn rd(&mut self, index: u16, buf: &mut [u8]) {
+        if index == 0x7fff {
+            buf.fill(self.page);
+            return;
+        }
+
+        match self.page {
+            2 if index >= UI_START => {
+                let i = (index - UI_START) as usize;
+                for (j, b) in buf.iter_mut().enumerate() {
+                    *b = self.ui.get(i+j).copied().unwrap_or(0);
+                }
+
+                // Command status; polled after each command. '[2]' >= 0x7f is an MCU error.
+                if i == 0 {
+                    let st: [u8;4] = if self.fw_ok() { [0x02, 0x03, 0x00, 0x00] } else { [0x02, 0x03, 0x7f, 0x00] };
+                    buf.iter_mut().zip(st).for_each(|(b, v)| *b = v);
+                }
+            },
+            2 => self.rd_results(index, buf),
+            p => {
+                for (j, b) in buf.iter_mut().enumerate() {
+                    *b = self.reg(p, index + j as u16);
+                }
+            }
+        }
+    }
+
+    fn wr(&mut self, index: u16, vs: &[u8]) {
+        if index == 0x7fff {
+            self.page = vs[0];
+            return;
+        }
+
+        match self.page {
+            p @ 9..=11 => {     // firmware upload; one write per page
+                if index == 0 && !vs.is_empty() {
+                    self.fw_pages |= 1 << (p - 9);
+                }
+            },
+            2 if index >= UI_START => {
+                let i = (index - UI_START) as usize;
+                assert!(i + vs.len() <= self.ui.len(), "write beyond the UI command area");
+
+                self.ui[i..i + vs.len()].copy_from_slice(vs);
+                if i + vs.len() == self.ui.len() {
+                    self.command(vs);
+                }
+            },
+            2 => {},    // results; read only
+            p => {
+                for (j, &v) in vs.iter().enumerate() {
+                    self.regs.insert((p, index + j as u16), v);
+                }
+                match (p, index) {
+                    (0, 0x04) => self.addr = I2cAddr::from_7bit(vs[0]),
+                    (0, 0x14) if vs[0] == 0x01 => self.ranging = None,      // MCU stop ('vl_stop_ranging()')
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn reg(&self, page: u8, index: u16) -> u8 {
+        let mcu_stopped = self.regs.get(&(0, 0x14)) == Some(&0x01);
+
+        match (page, index) {
+            (0, 0x00) => 0xf0,              // device id
+            (0, 0x01) => REV_ID,
+            (0, 0x06) => if mcu_stopped { 0x81 } else { 0x01 },     // GO2 status 0: booted (+ MCU stopped)
+            (0, 0x07) => 0x84,              // GO2 status 1: MCU stop acknowledged
+            (1, 0x21) => 0x10,              // firmware access enabled
+            _ => self.regs.get(&(page, index)).copied().unwrap_or(0)
+        }
+    }
+
+    /*
+    * A command, written so that it ends at 'UI_END'. The firmware would act on it, and place any
+    * answer at 'UI_START+4'.
+    *
+    * Other commands (getting the NVM data, sending offset and xtalk data, default configuration)
+    * need no reaction.
+    */
+    fn command(&mut self, vs: &[u8]) {
+        let n = vs.len();
+
+        if vs == START_CMD {
+            self.start();
+
+        } else if n == 12 && vs[4..] == DCI_READ_FOOTER {
+            let (idx, size) = dci_header(vs);
+
+            // '[header:4][data][footer:8]', 4-byte groups swapped (undone by 'vl_dci_read_data()')
+            let mut host = vec![0_u8; 4 + size + 8];
+            if let Some(d) = self.dci.get(&idx) {
+                let m = d.len().min(size);
+                host[4..4+m].copy_from_slice(&d[..m]);
+            }
+            swap4(&mut host);
+            self.ui[4..4 + host.len()].copy_from_slice(&host);
+
+        } else if n >= 12 && vs[n-4..n-2] == [0x05, 0x01] {     // DCI write footer
+            let (idx, size) = dci_header(vs);
+            if 4 + size + 8 == n {
+                let mut data = vs[4..4+size].to_vec();
+                swap4(&mut data);
+                self.dci.insert(idx, data);
+            }
+        }
+    }
+
+    fn start(&mut self) {
+        let words = |idx: u16| -> Vec<u32> {
+            self.dci.get(&idx)
+                .map(|d| d.chunks_exact(4).map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]])).collect())
+                .unwrap_or_default()
+        };
+
+        let size = words(DCI_OUTPUT_CONFIG).first().copied().unwrap_or(0);
+        let enables = words(DCI_OUTPUT_ENABLES);
+
+        let blocks: Vec<u32> = words(DCI_OUTPUT_LIST).into_iter().enumerate()
+            .filter(|&(i, bh)| bh != 0 && enables.get(i/32).is_some_and(|e| e & (1 << (i%32)) != 0))
+            .map(|(_, bh)| bh)
+            .filter(|bh| bh & 0xf != 0xd)   // the start block (type 0xd) is part of the frame header
+            .collect();
+
+        let mut rd = vec![0_u8; 12];
+        rd[8..].copy_from_slice(&size.to_le_bytes());
+        self.dci.insert(DCI_UI_RANGE_DATA, rd);
+
+        self.ranging = Some(Layout{ size: size as usize, blocks });
+        self.next_frame();
+    }
+
+    /*
+    * Reads of the results area. 'vl_check_data_ready()' reads the first 4 bytes; each such poll
+    * finds a new frame. 'vl_get_ranging_data()' then reads the whole frame.
+    */
+    fn rd_results(&mut self, index: u16, buf: &mut [u8]) {
+        buf.fill(0);
+        if self.ranging.is_none() || index != 0 { return; }
+
+        if buf.len() == 4 {
+            self.next_frame();
+        }
+        let n = buf.len().min(self.frame.len());
+        buf[..n].copy_from_slice(&self.frame[..n]);
+    }
+
+    /*
+    * Frame layout (host byte order; 'vl_get_ranging_data()' swaps it so):
+    *   [0..4]      data ready status, stream count in '[3]'
+    *   [8..10]     header id (BE); needs to match the footer id
+    *   [16..]      blocks: '[block header:4][data]'
+    *   ..          footer; footer id (BE) at 'size-4'
+    */
+    fn next_frame(&mut self) {
+        let Some(layout) = self.ranging.as_ref() else { return };
+
+        let stream = (self.stream + 1) % 255;       // 255 is not a valid stream count
+        let size = layout.size;
+        let id = (stream as u16).to_be_bytes();
+
+        let mut host = vec![0_u8; size];
+        host[..4].copy_from_slice(&[0x10, 0x05, 0x05, stream]);
+        host[8..10].copy_from_slice(&id);
+
+        let mut i = 16;
+        for &bh in &layout.blocks {
+            let (typ, n, idx) = (bh & 0xf, (bh >> 4) & 0xfff, (bh >> 16) as u16);
+            let msize = (if typ > 1 && typ < 0xd { typ * n } else { n }) as usize;
+
+            host[i..i+4].copy_from_slice(&bh.to_le_bytes());
+            self.fill(idx, &mut host[i+4..i+4+msize]);
+            i += 4 + msize;
+        }
+        assert!(i + 8 <= size, "blocks don't fit 'data_read_size'");
+
+        host[size-4..size-2].copy_from_slice(&id);
+        swap4(&mut host);
+
+        self.stream = stream;
+        self.frame = host;
+    }
+
+    fn fill(&self, idx: u16, payload: &mut [u8]) {
+        match idx {
+            METADATA_IDX => payload[8] = self.temp_degc as u8,
+            NB_TARGET_DETECTED_IDX => payload.fill(1),
+            TARGET_STATUS_IDX => payload.fill(5),   // "range valid"
+            DISTANCE_IDX => {
+                let v = (self.distance_mm as i16 * 4).to_le_bytes();    // 1/4 mm
+                payload.chunks_exact_mut(2).for_each(|w| w.copy_from_slice(&v));
+            },
+            _ => {}
+        }
+    }
+}
+
+// '(index, data size)' from the first 4 bytes of a DCI command
+fn dci_header(vs: &[u8]) -> (u16, usize) {
+    let idx = u16::from_be_bytes([vs[0], vs[1]]);
+    let size = ((vs[2] as usize) << 4) | ((vs[3] as usize) >> 4);
+    (idx, size)
+}
+
+// Like 'VL_SwapBuffer()': ABCD -> DCBA, for each full 4-byte group
+fn swap4(buf: &mut [u8]) {
+    buf.chunks_exact_mut(4).for_each(|w| w.reverse());
+}
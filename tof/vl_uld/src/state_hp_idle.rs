@@ -5,7 +5,7 @@
 *
 *   - HP Idle   // allows transition to the other two
 *   - Ranging
-*   - LP Idle   // we don't (currently) support this mode
+*   - LP Idle
 *
 * The larger point is that the Rust API reflects the states. You can have the sensor presented
 * as 'State_HP_Idle', but if you transit to ranging, you no longer have access to that state (unless
@@ -17,27 +17,27 @@
 use defmt::panic;
 
 use crate::{
-    platform,
+    platform::{self, PlatformError},
+    state_lp_idle::{SleepError, State_LP_Idle},
     state_ranging::{
         RangingConfig,
         State_Ranging,
     },
-    uld_raw::{
-        vl_get_power_mode,
-        VL_Configuration
-    },
+    uld::{with_uld, Uld},
     Error,
     I2cAddr,
     Result,
+    Variant,
     ST_OK
 };
 
 #[cfg(feature = "vl53l8cx")]
+use crate::uld_raw8::SyncMode as SyncMode_R;
+
+#[cfg(feature = "dci")]
 use crate::{
-    uld_raw::{
-        vl_set_external_sync_pin_enable,
-        SyncMode as SyncMode_R
-    }
+    dci::DciIndex,
+    error::DciError,
 };
 
 /*
@@ -58,20 +58,42 @@ pub struct State_HP_Idle {
     // The "state" can be read, but we "MUST not manually change these field[s]". In this Rust API,
     // the whole "state" is kept private, to enforce such read-only nature.
     //
-    uld: VL_Configuration,
+    // Note: It's the state of the sensor's variant; L5CX and L8CX differ (see 'uld.rs').
+    //
+    uld: Uld,
 }
 
 impl State_HP_Idle {
-    pub(crate) fn new(uld: VL_Configuration) -> Self {
+    pub(crate) fn new(uld: Uld) -> Self {
         Self{ uld }
     }
 
+    pub fn variant(&self) -> Variant {
+        self.uld.variant()
+    }
+
     //---
     // Ranging (getting values)
     //
-    pub fn start_ranging<const DIM: usize>(/*move*/ self, cfg: &RangingConfig<DIM>) -> Result<State_Ranging<DIM>> {
-        let r = State_Ranging::transition_from(self, cfg)?;
-        Ok(r)
+    // A bad 'cfg' is returned as 'Error::Config', before any change to the sensor. On any error,
+    // the sensor handle is given back (in the 'StartError'); it's still in HP Idle.
+    //
+    pub fn start_ranging<const DIM: usize>(/*move*/ self, cfg: &RangingConfig<DIM>) -> core::result::Result<State_Ranging<DIM>, StartError> {
+        if let Err(e) = cfg.check() {
+            return Err(StartError{ error: e.into(), vl: self });
+        }
+        State_Ranging::transition_from(self, cfg)
+    }
+
+    //---
+    // Low power
+    //
+    // Since the power mode is carried by the type, there's no 'get_power_mode' (ULD C API has one).
+    //
+    // On failure, the handle is given back (in the 'SleepError').
+    //
+    pub fn sleep(/*move*/ self) -> core::result::Result<State_LP_Idle, SleepError> {
+        State_LP_Idle::transition_from(self)
     }
 
     /*
@@ -85,14 +107,14 @@ impl State_HP_Idle {
 
         // Implementation based on ULD C API 'vl_set_i2c_address'
 
-        platform::with(&mut self.uld.platform, |pl| -> core::result::Result<(),()> {
+        platform::with(self.uld.platform(), |pl| -> core::result::Result<(),PlatformError> {
             pl.wr_bytes(0x7fff, &[0])?;
             pl.wr_bytes(0x4, &[addr.as_7bit()])?;
             pl.addr_changed(addr);
 
             pl.wr_bytes(0x7fff, &[2])?;  // now with the new I2C address
             Ok(())
-        }).expect("writing to I2C to succeed");
+        }).map_err(Error::Platform)?;
 
         // Further comms will happen to the new address. Let's still make a small access with the
         // new address, e.g. reading something.
@@ -108,10 +130,10 @@ impl State_HP_Idle {
     */
     pub /*<-- for debugging*/ fn i2c_no_op(&mut self) -> Result<()> {
         let mut tmp: u8 = 0;
-        match unsafe { vl_get_power_mode(&mut self.uld, &mut tmp) } {
+        with_uld!(&mut self.uld, |vl, raw| match unsafe { raw::vl_get_power_mode(vl, &mut tmp) } {
             ST_OK => Ok(()),
-            e => Err(Error(e))
-        }
+            e => Err(vl.error_from(e))
+        })
     }
 
     /*
@@ -120,53 +142,170 @@ impl State_HP_Idle {
     * Note: The setting only applies to "autonomous" mode. Instead of having it as a mode parameter,
     *       it's here as a "global" switch. However, even this arrangement guarantees the call is
     *       made in the HP Idle state, being a benefit over the ULD C API.
+    *
+    * Mixed builds: 'Error::NotSupported' for an L5CX.
     */
     #[cfg(feature = "vl53l8cx")]
     pub fn set_sync_pin_enable(&mut self, v: bool) -> Result<()> {
         let v: SyncMode_R = if v { SyncMode_R::SYNC } else { SyncMode_R::NONE };
 
-        match unsafe { vl_set_external_sync_pin_enable(&mut self.uld, v as u8) } {
-            ST_OK => Ok(()),
-            e => Err(Error(e))
+        match &mut self.uld {
+            Uld::L8(vl) => match unsafe { crate::uld_raw8::vl_set_external_sync_pin_enable(vl, v as u8) } {
+                ST_OK => Ok(()),
+                e => Err(vl.error_from(e))
+            },
+            #[cfg(uld_mixed)]
+            uld => Err(Error::NotSupported(uld.variant()))
         }
     }
 
-    pub(crate) fn borrow_uld_mut(&mut self) -> &mut VL_Configuration {
-        &mut self.uld
+    /*
+    * Internal charge pump control. L5CX only (the L7CX vendor sources haven't been checked for it).
+    *
+    * The charge pump is needed when AVDD is 2V8 (enabled by default). With 3V3 AVDD, it can be
+    * disabled to save power. Vendor docs: "[...] if the AVDD is 3.3V, the internal charge pump can be
+    * disabled".
+    *
+    * Mixed builds: 'Error::NotSupported' for an L8CX.
+    */
+    #[cfg(feature = "vl53l5cx")]
+    pub fn set_internal_cp_enable(&mut self, v: bool) -> Result<()> {
+        use crate::uld_raw5::{vl_disable_internal_cp, vl_enable_internal_cp};
+
+        match &mut self.uld {
+            Uld::L5(vl) => match unsafe { if v { vl_enable_internal_cp(vl) } else { vl_disable_internal_cp(vl) } } {
+                ST_OK => Ok(()),
+                e => Err(vl.error_from(e))
+            },
+            #[cfg(uld_mixed)]
+            uld => Err(Error::NotSupported(uld.variant()))
+        }
+    }
+
+    /*
+    * VHV ("very high voltage") recalibration, during ranging.
+    *
+    * The sensor calibrates its SPAD voltage at start of ranging. If temperature changes while
+    * ranging, it can redo this every 'n' frames. 0 = disabled (vendor default).
+    */
+    pub fn get_vhv_repeat_count(&mut self) -> Result<u32> {
+        let mut tmp: u32 = 0;
+        with_uld!(&mut self.uld, |vl, raw| match unsafe { raw::vl_get_VHV_repeat_count(vl, &mut tmp) } {
+            ST_OK => Ok(tmp),
+            e => Err(vl.error_from(e))
+        })
     }
 
-    /*** disabled (until we try/need low power)
-    // tbd. Does setting low power mode mean transitioning to 'LP_Idle'?  In that case, this should
-    //      be state transition for us (and 'get_power_mode' is not needed, since it's implied by
-    //      the Rust object the application has access to!
+    pub fn set_vhv_repeat_count(&mut self, n: u32) -> Result<()> {
+        with_uld!(&mut self.uld, |vl, raw| match unsafe { raw::vl_set_VHV_repeat_count(vl, n) } {
+            ST_OK => Ok(()),
+            e => Err(vl.error_from(e))
+        })
+    }
+
+    pub(crate) fn borrow_uld_mut(&mut self) -> &mut Uld {
+        &mut self.uld
+    }
 
     //---
-    // Maintenance; special use
+    // DCI access (undocumented firmware parameters); see 'dci.rs'.
     //
-    pub fn get_power_mode(&mut self) -> Result<PowerMode> {
-        let mut tmp: u8 = 0;
-        match unsafe { vl_get_power_mode(&mut self.uld, &mut tmp) } {
-            ST_OK => Ok(PowerMode::from_repr(tmp).unwrap()),
-            e => Err(Error(e))
-        }
+    // The data sizes need to be multiples of 4: the ULD C code swaps the bytes in 4-byte groups.
+    // Buffers that aren't fit are returned as 'Error::Dci', without talking to the sensor.
+    //
+    #[cfg(feature = "dci")]
+    pub fn dci_read_data(&mut self, index: DciIndex, buf: &mut [u8]) -> Result<()> {
+        check_dci_buf(buf, false)?;
+
+        with_uld!(&mut self.uld, |vl, raw| match unsafe { raw::vl_dci_read_data(vl, buf.as_mut_ptr(), index.0 as u32, buf.len() as u16) } {
+            ST_OK => Ok(()),
+            e => Err(vl.error_from(e))
+        })
     }
-    pub fn set_power_mode(&mut self, v: PowerMode) -> Result<()> {
-        match unsafe { vl_set_power_mode(&mut self.uld, v as u8) } {
+
+    /*
+    * Note: The buffer is '&mut' since the ULD C code swaps its bytes in place (and back), i.e. it
+    *       needs to be 'u32' aligned. Use 'dci::Aligned' for that. The contents are the same after
+    *       the call.
+    */
+    #[cfg(feature = "dci")]
+    pub fn dci_write_data(&mut self, index: DciIndex, buf: &mut [u8]) -> Result<()> {
+        check_dci_buf(buf, true)?;
+
+        with_uld!(&mut self.uld, |vl, raw| match unsafe { raw::vl_dci_write_data(vl, buf.as_mut_ptr(), index.0 as u32, buf.len() as u16) } {
             ST_OK => Ok(()),
-            e => Err(Error(e))
+            e => Err(vl.error_from(e))
+        })
+    }
+
+    /*
+    * Read-modify-write: reads 'buf.len()' bytes from 'index', places 'new_data' at 'pos' and writes
+    * the result back. 'buf' is used as the working area (and carries the written data, afterwards).
+    *
+    * This is what the ULD C code itself does e.g. for the glare filter.
+    *
+    * Note: The write swaps 'buf' in place, like with '.dci_write_data()'; it needs to be 'u32' aligned.
+    */
+    #[cfg(feature = "dci")]
+    pub fn dci_replace_data(&mut self, index: DciIndex, buf: &mut [u8], new_data: &[u8], pos: u16) -> Result<()> {
+        check_dci_buf(buf, true)?;
+        if pos as usize + new_data.len() > buf.len() {
+            return Err(DciError::Overflow{ pos, len: new_data.len() }.into());
         }
+
+        // Note: 'new_data' is only read by the C side (the prototype just lacks the 'const').
+        with_uld!(&mut self.uld, |vl, raw| match unsafe { raw::vl_dci_replace_data(vl, buf.as_mut_ptr(), index.0 as u32, buf.len() as u16,
+            new_data.as_ptr() as *mut u8, new_data.len() as u16, pos)
+        } {
+            ST_OK => Ok(()),
+            e => Err(vl.error_from(e))
+        })
+    }
+}
+
+/*
+* Size (and, for buffers the C side swaps in place, alignment) checks for the DCI functions.
+*/
+#[cfg(feature = "dci")]
+fn check_dci_buf(buf: &[u8], swapped: bool) -> core::result::Result<(), DciError> {
+    if buf.len() % 4 != 0 || buf.len() > u16::MAX as usize {
+        Err(DciError::Size(buf.len()))
+    } else if swapped && buf.as_ptr() as usize % 4 != 0 {
+        Err(DciError::Unaligned)
+    } else {
+        Ok(())
+    }
+}
+
+/*
+* A failed '.start_ranging()': the reason, and the sensor handle back.
+*
+* Converts to 'Error' (with '?'), for applications not wanting to keep the handle.
+*/
+pub struct StartError {
+    pub error: Error,
+    pub vl: State_HP_Idle,
+}
+
+impl core::fmt::Debug for StartError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self.error)
     }
-    ***/
+}
 
-    // tbd. if exposing these, make them into a "dci" feature
-    //pub fn dci_read_data(index: u16, buf: &mut [u8]) { unimplemented!() }
-    //pub fn dci_write_data(index: u16, buf: &[u8]) { unimplemented!() }
+impl core::fmt::Display for StartError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
 
-    // 'dci_replace_data' doesn't seem useful; easily reproduced using the 'read' and 'write'. Skip.
+#[cfg(feature = "defmt")]
+impl defmt::Format for StartError {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", self.error)
+    }
+}
 
-    // Remaining to be implemented:
-    //  vl_enable_internal_cp()
-    //  vl_disable_internal_cp
-    //  vl_set_VHV_repeat_count
-    //  vl_get_VHV_repeat_count
-}
\ No newline at end of file
+impl From<StartError> for Error {
+    fn from(e: StartError) -> Self { e.error }
+}
@@ -0,0 +1,134 @@
+/*
+* State_LP_Idle
+*
+* "Low power" idle state of the sensor (vendor terminology; ULD C API 'VL_POWER_MODE_SLEEP').
+*
+* The firmware and configuration are retained on the sensor, but it doesn't range. Only 'HP Idle'
+* can transition here, and the only way out is back to 'HP Idle'. The ULD C API has a getter for
+* the power mode; we don't need it, since the Rust type (the one the application holds) tells it.
+*
+* Note: L8 also has a "deep sleep" mode, where the firmware gets lost. Not supported: it's
+*       practically the same as powering the sensor off and re-initializing it.
+*/
+#[cfg(feature = "defmt")]
+#[allow(unused_imports)]
+use defmt::trace;
+
+use crate::{
+    state_hp_idle::State_HP_Idle,
+    uld::{with_uld, Uld},
+    uld_raw::{
+        PowerMode as PowerMode_R,
+        ST_OK,
+    },
+    Error,
+    Result,
+};
+
+#[allow(non_camel_case_types)]
+pub struct State_LP_Idle {
+    outer_state: State_HP_Idle,
+}
+
+impl State_LP_Idle {
+    pub(crate) fn transition_from(/*move*/ mut st: State_HP_Idle) -> core::result::Result<Self, SleepError> {
+        if let Err(error) = Self::set_power_mode(st.borrow_uld_mut(), PowerMode_R::SLEEP) {
+            return Err(SleepError{ error, vl: st });
+        }
+
+        #[cfg(feature = "defmt")]
+        trace!("Sensor now in LP Idle");
+
+        Ok(Self{ outer_state: st })
+    }
+
+    /*
+    * Wake up the sensor; provides access back to the 'HP Idle' state.
+    *
+    * On failure, the handle is given back (in the 'WakeError'); the wake up can be retried.
+    */
+    pub fn wake_up(mut self) -> core::result::Result<State_HP_Idle, WakeError> {
+        if let Err(error) = Self::set_power_mode(self.outer_state.borrow_uld_mut(), PowerMode_R::WAKEUP) {
+            return Err(WakeError{ error, vl: self });
+        }
+
+        Ok(self.outer_state)
+    }
+
+    fn set_power_mode(uld: &mut Uld, v: PowerMode_R) -> Result<()> {
+        with_uld!(uld, |vl, raw| match unsafe { raw::vl_set_power_mode(vl, v as u8) } {
+            ST_OK => Ok(()),
+            e => Err(vl.error_from(e))
+        })
+    }
+}
+
+/*
+* A failed '.sleep()': the reason, and the (HP Idle) sensor handle back.
+*
+* Note: The ULD reads the power mode back before changing it, and polls for the change. Depending
+*       on where the failure happened, the sensor may already be asleep. '.sleep()' can be retried;
+*       it's a no-op for the sensor, if so.
+*
+* Converts to 'Error' (with '?'), for applications not wanting to keep the handle.
+*/
+pub struct SleepError {
+    pub error: Error,
+    pub vl: State_HP_Idle,
+}
+
+impl core::fmt::Debug for SleepError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self.error)
+    }
+}
+
+impl core::fmt::Display for SleepError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for SleepError {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", self.error)
+    }
+}
+
+impl From<SleepError> for Error {
+    fn from(e: SleepError) -> Self { e.error }
+}
+
+/*
+* A failed '.wake_up()': the reason, and the (LP Idle) sensor handle back.
+*
+* Converts to 'Error' (with '?'), for applications not wanting to keep the handle.
+*/
+pub struct WakeError {
+    pub error: Error,
+    pub vl: State_LP_Idle,
+}
+
+impl core::fmt::Debug for WakeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self.error)
+    }
+}
+
+impl core::fmt::Display for WakeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for WakeError {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", self.error)
+    }
+}
+
+impl From<WakeError> for Error {
+    fn from(e: WakeError) -> Self { e.error }
+}
@@ -8,14 +8,20 @@
 
 #[cfg(feature = "defmt")]
 #[allow(unused_imports)]
-use defmt::{assert, panic, trace, debug};
+use defmt::{assert, panic, trace, debug, warn};
 
-use crate::uld_raw::{VL_Configuration, vl_start_ranging, vl_check_data_ready, vl_get_ranging_data, vl_set_resolution, vl_set_ranging_frequency_hz, vl_set_ranging_mode, vl_set_integration_time_ms, vl_set_sharpener_percent, vl_set_target_order, vl_stop_ranging, RangingMode as RangingMode_R, Resolution as Resolution_R, ST_OK, TargetOrder as TargetOrder_R, VL_ResultsData};
+use crate::uld_raw::{RangingMode as RangingMode_R, Resolution as Resolution_R, ST_OK, TargetOrder as TargetOrder_R};
+
+#[cfg(feature = "detection_thresholds")]
+use crate::thresholds::ThresholdConfig;
 
 use crate::{
+    platform::{self, AsyncPlatform},
     results_data::ResultsData,
-    state_hp_idle::State_HP_Idle,
+    state_hp_idle::{State_HP_Idle, StartError},
+    uld::{with_uld, Uld},
     units::{MsU16, HzU8, PrcU8, TempC, ExtU32 as _},
+    ConfigError,
     Error,
     Result,
 };
@@ -94,12 +100,17 @@ impl TargetOrder {
 *   - Integration time and frequency only apply to AUTONOMOUS ranging mode
 *   - Integration time range is (for all resolutions): [2ms..1000ms]; inclusive
 *   - Sharpener range is [0..99]; inclusive; (0 = disabled)
+*   - Motion indicator distances are [400..4000]mm, and the window max. 1500mm wide
 */
 #[derive(Clone)]
 pub struct RangingConfig<const DIM: usize> {    // |*|
     mode: Mode,      // also carries ranging frequency and integration time for 'AUTONOMOUS'
     sharpener: Option<PrcU8>,       // value range: 1..=99
     target_order: TargetOrder,
+    #[cfg(feature = "motion_indicator")]
+    motion_window: (u16,u16),       // (min, max) mm; motion is only tracked for targets within
+    #[cfg(feature = "detection_thresholds")]
+    thresholds: Option<ThresholdConfig<DIM>>,   // 'None': 'INT' on each frame
 }
     // |*|: decided to /not/ provide a '= 4' default for the 'DIM'. It *would work*, and slightly
     //      make it easier for the _application layer_, but it also messes with compile errors,
@@ -111,9 +122,10 @@ pub struct RangingConfig<const DIM: usize> {    // |*|
 
 impl<const DIM: usize> RangingConfig<DIM> {
     /* We allow construction to make potentially incompatible combinations, but check them within
-    * '.apply()'. This is a compromise between simplicity and robustness. Note that some obvious
-    * type-system robustness has been done, e.g. bundling ranging frequency and integration times
-    * with the ranging mode (since those only apply to one mode).
+    * '.check()' (also done when ranging starts). This is a compromise between simplicity and
+    * robustness. Note that some obvious type-system robustness has been done, e.g. bundling
+    * ranging frequency and integration times with the ranging mode (since those only apply to
+    * one mode).
     */
     pub fn with_sharpener(/*move*/ self, v: PrcU8) -> Self {
         Self { sharpener: Some(v), ..self }
@@ -127,13 +139,62 @@ impl<const DIM: usize> RangingConfig<DIM> {
         Self { mode, ..self }
     }
 
-    fn validate(&self) {
+    /*
+    * Distance window (mm) within which the motion indicator tracks targets.
+    */
+    #[cfg(feature = "motion_indicator")]
+    pub fn with_motion_window(/*move*/ self, min_mm: u16, max_mm: u16) -> Self {
+        Self { motion_window: (min_mm, max_mm), ..self }
+    }
+
+    /*
+    * With thresholds, the sensor only flags data as ready (and raises 'INT') when the criteria
+    * are met.
+    */
+    #[cfg(feature = "detection_thresholds")]
+    pub fn with_thresholds(/*move*/ self, ths: ThresholdConfig<DIM>) -> Self {
+        Self { thresholds: Some(ths), ..self }
+    }
+
+    /*
+    * Ranging frequency; 'None' for 'CONTINUOUS' (we don't set it, for that mode).
+    */
+    pub fn frequency(&self) -> Option<HzU8> {
+        match self.mode {
+            AUTONOMOUS(_, freq) => Some(freq),
+            CONTINUOUS => None
+        }
+    }
+
+    /*
+    * With detection thresholds, 'INT' is only raised when they are met; frames may not arrive
+    * at the ranging frequency.
+    */
+    #[cfg(feature = "detection_thresholds")]
+    pub fn has_thresholds(&self) -> bool {
+        self.thresholds.is_some()
+    }
+
+    /*
+    * Check the config against the vendor limits (listed above), without touching the sensor.
+    *
+    * This is also done by 'start_ranging'; applications can use it to reject e.g. user-provided
+    * settings beforehand.
+    */
+    pub fn check(&self) -> core::result::Result<(), ConfigError> {
         let (_,R_INTEGRATION_TIMES_N, R_FREQ_RANGE_MAX): (_,u8,HzU8) = reso_details::<DIM>();
 
         match self.mode {
             AUTONOMOUS(MsU16(integration_time_ms), HzU8(freq), ..) => {
-                assert!((2..=1000).contains(&integration_time_ms), "Integration time out of range");
-                    // Source: comment in (and code of) vendor ULD C sources
+                if !(2..=1000).contains(&integration_time_ms) {
+                    return Err(ConfigError::IntegrationTime(integration_time_ms));
+                }   // Source: comment in (and code of) vendor ULD C sources
+
+                // Checked before the window, since that divides by 'freq'.
+                let freq_range = 1..=R_FREQ_RANGE_MAX.0;    // 1..=60 (4x4); 1..=15 (8x8)
+                if !freq_range.contains(&freq) {
+                    return Err(ConfigError::Frequency(freq));
+                }
 
                 // "The sum of all integration times + 1 ms overhead must be lower than the measurement
                 // period. Otherwise, the ranging period is automatically increased." (src: UM2884 - Rev 5 p.9)
@@ -141,67 +202,116 @@ impl<const DIM: usize> RangingConfig<DIM> {
                 // "4x4 is composed of ONE integration time"
                 // "8x8 is composed of FOUR integration times" (same src)
                 //
-                let n = R_INTEGRATION_TIMES_N;  // 1 (4x4); 4 (8x8)
+                let n = R_INTEGRATION_TIMES_N as u32;  // 1 (4x4); 4 (8x8)
 
                 // Note: The test itself is calculated so that inaccuracies don't occur (multiplication instead of division).
-                //      The error message parameter is let go more loosely; not a problem.
+                //      'max_ms' is the largest value passing it: '(max_ms+1)*n*freq <= 999'.
                 //
-                assert!((integration_time_ms as u16+1)*(n as u16)*(freq as u16) < 1000,
-                    "Integration time exceeds the available window ({}ms)", (1000_u16/(n as u16 * freq as u16))-1
-                );
-
-                let freq_range = 1..(R_FREQ_RANGE_MAX.0 as u8);    // 1..15 (4x4); 1..60 (8x8)
-                assert!(freq_range.contains(&freq), "Frequency out of range");
+                if (integration_time_ms as u32 + 1) * n * (freq as u32) >= 1000 {
+                    return Err(ConfigError::IntegrationWindow{ max_ms: ((999 / (n * freq as u32)) - 1) as u16 });
+                }
             },
             _ => {}
         }
 
         match self.sharpener {
-            Some(PrcU8(v)) => { assert!((1..=99).contains(&v), "Sharpener out of range (1..=99)") },
-            None => {}
+            Some(PrcU8(v)) if !(1..=99).contains(&v) => return Err(ConfigError::Sharpener(v)),
+            _ => {}
+        }
+
+        // Source: checks in the vendor's motion indicator plugin (C). The vendor also lets through
+        // 'min > max' (its difference goes negative); we don't, since such a window is empty.
+        #[cfg(feature = "motion_indicator")]
+        {
+            let (min, max) = self.motion_window;
+            if !(400 <= min && min <= max && max <= 4000 && max - min <= 1500) {
+                return Err(ConfigError::MotionWindow(min, max));
+            }
+        }
+
+        #[cfg(feature = "detection_thresholds")]
+        if let Some(ths) = &self.thresholds {
+            ths.check()?;
         }
+
+        Ok(())
     }
 
-    fn apply(&self, vl: &mut VL_Configuration) -> Result<()> {
-        self.validate();    // may panic
+    // Note: '.check()' is expected to have passed (done by 'State_HP_Idle::start_ranging()').
+    //
+    fn apply(&self, uld: &mut Uld) -> Result<()> {
         let /*const*/ ULD_RESO: Resolution_R = reso_details::<DIM>().0;
 
-        // Set the resolution first. UM2884 (Rev 5) says:
-        //  "['..._set_resolution()'] must be used before updating the ranging frequency"
+        with_uld!(uld, |vl, raw| {
+            // Set the resolution first. UM2884 (Rev 5) says:
+            //  "['..._set_resolution()'] must be used before updating the ranging frequency"
 
-        match unsafe { vl_set_resolution(vl, ULD_RESO as u8) } {  // reso value: 16 (4x4); 64 (8x8)
-            ST_OK => Ok(()),
-            e => Err(Error(e))
-        }?;
+            match unsafe { raw::vl_set_resolution(vl, ULD_RESO as u8) } {  // reso value: 16 (4x4); 64 (8x8)
+                ST_OK => Ok(()),
+                e => Err(vl.error_from(e))
+            }?;
+
+            if let AUTONOMOUS(MsU16(ms), HzU8(freq), ..) = self.mode {
+                match unsafe { raw::vl_set_integration_time_ms(vl, ms as u32) } {
+                    ST_OK => Ok(()),
+                    e => Err(vl.error_from(e))
+                }?;
+                match unsafe { raw::vl_set_ranging_frequency_hz(vl, freq as u8) } {
+                    ST_OK => Ok(()),
+                    e => Err(vl.error_from(e))
+                }?;
+            }
 
-        if let AUTONOMOUS(MsU16(ms), HzU8(freq), ..) = self.mode {
-            match unsafe { vl_set_integration_time_ms(vl, ms as u32) } {
+            match unsafe { raw::vl_set_ranging_mode(vl, self.mode.as_uld() as _) } {
                 ST_OK => Ok(()),
-                e => Err(Error(e))
+                e => Err(vl.error_from(e))
             }?;
-            match unsafe { vl_set_ranging_frequency_hz(vl, freq as u8) } {
+
+            let sharpener_prc: u8 = match self.sharpener {
+                Some(PrcU8(v)) => v,
+                None => 0
+            };
+            match unsafe { raw::vl_set_sharpener_percent(vl, sharpener_prc) } {
                 ST_OK => Ok(()),
-                e => Err(Error(e))
+                e => Err(vl.error_from(e))
             }?;
-        }
 
-        match unsafe { vl_set_ranging_mode(vl, self.mode.as_uld() as _) } {
-            ST_OK => Ok(()),
-            e => Err(Error(e))
-        }?;
+            match unsafe { raw::vl_set_target_order(vl, self.target_order.as_uld() as _) } {
+                ST_OK => Ok(()),
+                e => Err(vl.error_from(e))
+            }?;
 
-        let sharpener_prc: u8 = match self.sharpener {
-            Some(PrcU8(v)) => v,
-            None => 0
-        };
-        match unsafe { vl_set_sharpener_percent(vl, sharpener_prc) } {
-            ST_OK => Ok(()),
-            e => Err(Error(e))
-        }?;
+            // Motion indicator config depends on the resolution (done, above).
+            //
+            // Note: The plugin's config struct is only needed while writing it to the sensor; we don't
+            //      need to keep it around.
+            //
+            #[cfg(feature = "motion_indicator")]
+            {
+                use core::mem::MaybeUninit;
+                let mut mcfg: raw::VL_Motion_Configuration = unsafe { MaybeUninit::zeroed().assume_init() };
+                let (min, max) = self.motion_window;
+
+                match unsafe { raw::vl_motion_indicator_init(vl, &mut mcfg, ULD_RESO as u8) } {
+                    ST_OK => Ok(()),
+                    e => Err(vl.error_from(e))
+                }?;
+                match unsafe { raw::vl_motion_indicator_set_distance_motion(vl, &mut mcfg, min, max) } {
+                    ST_OK => Ok(()),
+                    e => Err(vl.error_from(e))
+                }?;
+            }
 
-        match unsafe { vl_set_target_order(vl, self.target_order.as_uld() as _) } {
-            ST_OK => Ok(()),
-            e => Err(Error(e))
+            Ok::<(), Error>(())
+        })?;
+
+        // Thresholds are programmed last, since they depend on the resolution (zone numbers).
+        // If none are given, make sure earlier sessions' thresholds don't linger.
+        //
+        #[cfg(feature = "detection_thresholds")]
+        match &self.thresholds {
+            Some(ths) => ths.apply(uld),
+            None => ThresholdConfig::<DIM>::enable(uld, false)
         }?;
 
         Ok(())
@@ -217,6 +327,10 @@ impl<const DIM: usize> Default for RangingConfig<DIM> {
             sharpener: None,
             target_order: STRONGEST,
             mode: AUTONOMOUS(5.ms(),HzU8(1) /*1.Hz()*/),
+            #[cfg(feature = "motion_indicator")]
+            motion_window: (1000, 2000),    // as in vendor's example
+            #[cfg(feature = "detection_thresholds")]
+            thresholds: None,
         }
     }
 }
@@ -229,18 +343,24 @@ pub struct State_Ranging<const DIM: usize> {    // DIM: 4|8
 }
 
 impl<const DIM: usize> State_Ranging<DIM> {
-    pub(crate) fn transition_from(/*move*/ mut st: State_HP_Idle, cfg: &RangingConfig<DIM>) -> Result<Self> {
-        let vl: &mut VL_Configuration = st.borrow_uld_mut();
-        cfg.apply(vl)?;
+    pub(crate) fn transition_from(/*move*/ mut st: State_HP_Idle, cfg: &RangingConfig<DIM>) -> core::result::Result<Self, StartError> {
+        let uld: &mut Uld = st.borrow_uld_mut();
+
+        let r = cfg.apply(uld).and_then(|_|
+            with_uld!(uld, |vl, raw| match unsafe { raw::vl_start_ranging(vl) } {
+                ST_OK => Ok(()),
+                e => Err(vl.error_from(e))
+            })
+        );
 
-        match unsafe { vl_start_ranging(vl) } {
-            ST_OK => {
+        match r {
+            Ok(()) => {
                 let x = Self{
                     outer_state: Some(st),
                 };
                 Ok(x)
             },
-            e => Err(Error(e))
+            Err(error) => Err(StartError{ error, vl: st })
         }
     }
 
@@ -252,10 +372,27 @@ impl<const DIM: usize> State_Ranging<DIM> {
     */
     pub fn is_ready(&mut self) -> Result<bool> {
         let mut tmp: u8 = 0;
-        match unsafe { vl_check_data_ready(self.borrow_uld_mut(), &mut tmp) } {
+        with_uld!(self.borrow_uld_mut(), |vl, raw| match unsafe { raw::vl_check_data_ready(vl, &mut tmp) } {
             ST_OK => Ok(tmp != 0),
-            e => Err(Error(e))
-        }
+            e => Err(vl.error_from(e))
+        })
+    }
+
+    /*
+    * Like '.is_ready()', but the status bytes are read via 'ap', awaiting the I2C.
+    *
+    * Note: 'ap' must talk to the same sensor (I2C address) as the blocking 'Platform'.
+    */
+    pub async fn is_ready_async<A: AsyncPlatform>(&mut self, ap: &mut A) -> Result<bool> {
+        const INDEX: u16 = 0x0000;  // 'vl_check_data_ready()' reads 4 bytes from here
+        const N: u32 = 4;
+
+        let uld = self.borrow_uld_mut();
+        ap.rd_bytes(INDEX, &mut uld.temp_buffer()[..N as usize]) .await
+            .map_err(|e| Error::Platform(e.into()))?;
+
+        platform::set_prefetched(uld.platform(), INDEX, N);
+        self.is_ready()
     }
 
     /*
@@ -266,25 +403,46 @@ impl<const DIM: usize> State_Ranging<DIM> {
         use core::mem::MaybeUninit;
         use core::ptr::addr_of_mut;
 
-        // The 'i8' field within the struct needs explicit initialization.
-        // See -> https://doc.rust-lang.org/std/mem/union.MaybeUninit.html#initializing-a-struct-field-by-field
-        //
-        let mut buf: VL_ResultsData = {
-            let mut un = MaybeUninit::<VL_ResultsData>::uninit();
-            let up = un.as_mut_ptr();
-            unsafe {
-                addr_of_mut!((*up).silicon_temp_degc).write(0);
-                un.assume_init()
+        with_uld!(self.borrow_uld_mut(), |vl, raw| {
+            // The 'i8' field within the struct needs explicit initialization.
+            // See -> https://doc.rust-lang.org/std/mem/union.MaybeUninit.html#initializing-a-struct-field-by-field
+            //
+            let mut buf: raw::VL_ResultsData = {
+                let mut un = MaybeUninit::<raw::VL_ResultsData>::uninit();
+                let up = un.as_mut_ptr();
+                unsafe {
+                    addr_of_mut!((*up).silicon_temp_degc).write(0);
+                    un.assume_init()
+                }
+            };
+
+            match unsafe { raw::vl_get_ranging_data(vl, &mut buf) } {
+                ST_OK => {
+                    let tuple = ResultsData::<DIM>::from(&buf);
+                    Ok(tuple)
+                },
+                e => Err(vl.error_from(e))
             }
-        };
+        })
+    }
 
-        match unsafe { vl_get_ranging_data(self.borrow_uld_mut(), &mut buf) } {
-            ST_OK => {
-                let tuple = ResultsData::<DIM>::from(&buf);
-                Ok(tuple)
-            },
-            e => Err(Error(e))
-        }
+    /*
+    * Like '.get_data()', but the results block is read via 'ap', awaiting the I2C. The ULD C code
+    * then finds the data already in place, and only parses it.
+    *
+    * Note: 'ap' must talk to the same sensor (I2C address) as the blocking 'Platform'.
+    */
+    pub async fn get_data_async<A: AsyncPlatform>(&mut self, ap: &mut A) -> Result<(ResultsData<DIM>, TempC)> {
+        const INDEX: u16 = 0x0000;  // 'vl_get_ranging_data()' reads the block from here
+
+        let uld = self.borrow_uld_mut();
+        let n = uld.data_read_size();      // set by 'vl_start_ranging()'
+
+        ap.rd_bytes(INDEX, &mut uld.temp_buffer()[..n as usize]) .await
+            .map_err(|e| Error::Platform(e.into()))?;
+
+        platform::set_prefetched(uld.platform(), INDEX, n);
+        self.get_data()
     }
 
     /*
@@ -299,19 +457,27 @@ impl<const DIM: usize> State_Ranging<DIM> {
         }
     }
 
+    /*
+    * Give up the session without talking to the sensor; e.g. when it's stalled, and is about to
+    * be power cycled. Dropping would try to stop the ranging (and log, if that fails).
+    */
+    pub fn abandon(mut self) {
+        let _ = self.outer_state.take();
+    }
+
     /*
     * Lower level "stop", usable by both the explicit '.stop()' and 'Drop' handler.
     *
     * Takes '&mut Self': 'Drop' handler cannot call the normal '.stop()' that consumes the struct.
     */
     fn _stop(outer: &mut State_HP_Idle) -> Result<()> {
-        match unsafe { vl_stop_ranging(outer.borrow_uld_mut()) } {
+        with_uld!(outer.borrow_uld_mut(), |vl, raw| match unsafe { raw::vl_stop_ranging(vl) } {
             ST_OK => Ok(()),
-            e => Err(Error(e))
-        }
+            e => Err(vl.error_from(e))
+        })
     }
 
-    fn borrow_uld_mut(&mut self) -> &mut VL_Configuration {
+    fn borrow_uld_mut(&mut self) -> &mut Uld {
         self.outer_state.as_mut().unwrap().borrow_uld_mut()
     }
 }
@@ -319,6 +485,9 @@ impl<const DIM: usize> State_Ranging<DIM> {
 /*
 * A Drop handler, so the ranging will seize (on the sensor) if the application simply drops the
 * state (instead of turning it back to 'HP Idle').
+*
+* A failure to stop is logged, not raised: the platform may not be able to reach the bus at this
+* point (e.g. it's shared, and someone else has it). Use '.stop()' to see the errors.
 */
 impl<const DIM: usize> Drop for State_Ranging<DIM> {
     fn drop(&mut self) {
@@ -328,8 +497,105 @@ impl<const DIM: usize> Drop for State_Ranging<DIM> {
         for mut outer in self.outer_state.as_mut() {
             match Self::_stop(&mut outer) {
                 Ok(_) => {},
-                Err(Error(e)) => { panic!("Stop ranging failed; st={}", e) }
+                #[cfg(feature = "defmt")]
+                Err(Error::Uld(st)) => { warn!("Stop ranging failed; st={}", st) }
+                #[cfg(feature = "defmt")]
+                Err(e) => { warn!("Stop ranging failed: {}", e) }
+                #[cfg(not(feature = "defmt"))]
+                Err(_) => {}
             }
         }
     }
 }
+
+/*
+* Run on the host, e.g.:
+*   <<
+*       $ cargo test --target x86_64-unknown-linux-gnu --features=vl53l5cx --lib
+*   <<
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn autonomous<const DIM: usize>(ms: u16, hz: u8) -> RangingConfig<DIM> {
+        RangingConfig::<DIM>::default().with_mode(AUTONOMOUS(MsU16(ms), HzU8(hz)))
+    }
+
+    #[test]
+    fn defaults_pass() {
+        assert_eq!(RangingConfig::<4>::default().check(), Ok(()));
+        assert_eq!(RangingConfig::<8>::default().check(), Ok(()));
+        assert_eq!(RangingConfig::<4>::default().with_mode(CONTINUOUS).check(), Ok(()));
+    }
+
+    #[test]
+    fn integration_time() {
+        assert_eq!(autonomous::<4>(2, 1).check(), Ok(()));
+        assert_eq!(autonomous::<4>(1000, 1).check(), Err(ConfigError::IntegrationWindow{ max_ms: 998 }));
+        assert_eq!(autonomous::<4>(1, 1).check(), Err(ConfigError::IntegrationTime(1)));
+        assert_eq!(autonomous::<4>(1001, 1).check(), Err(ConfigError::IntegrationTime(1001)));
+    }
+
+    // Both ends of the frequency range are inclusive.
+    #[test]
+    fn frequency() {
+        assert_eq!(autonomous::<4>(5, 60).check(), Ok(()));
+        assert_eq!(autonomous::<4>(5, 61).check(), Err(ConfigError::Frequency(61)));
+        assert_eq!(autonomous::<8>(5, 15).check(), Ok(()));
+        assert_eq!(autonomous::<8>(5, 16).check(), Err(ConfigError::Frequency(16)));
+        assert_eq!(autonomous::<4>(5, 0).check(), Err(ConfigError::Frequency(0)));
+    }
+
+    #[test]
+    fn integration_window() {
+        assert_eq!(autonomous::<4>(98, 10).check(), Ok(()));
+        assert_eq!(autonomous::<4>(99, 10).check(), Err(ConfigError::IntegrationWindow{ max_ms: 98 }));
+        assert_eq!(autonomous::<8>(23, 10).check(), Ok(()));
+        assert_eq!(autonomous::<8>(24, 10).check(), Err(ConfigError::IntegrationWindow{ max_ms: 23 }));
+
+        // Period not a whole number of ms
+        assert_eq!(autonomous::<4>(15, 60).check(), Ok(()));
+        assert_eq!(autonomous::<4>(16, 60).check(), Err(ConfigError::IntegrationWindow{ max_ms: 15 }));
+    }
+
+    #[test]
+    fn sharpener() {
+        let c = |v| RangingConfig::<4>::default().with_sharpener(PrcU8(v)).check();
+
+        assert_eq!(c(1), Ok(()));
+        assert_eq!(c(99), Ok(()));
+        assert_eq!(c(0), Err(ConfigError::Sharpener(0)));
+        assert_eq!(c(100), Err(ConfigError::Sharpener(100)));
+    }
+
+    #[cfg(feature = "motion_indicator")]
+    #[test]
+    fn motion_window() {
+        let c = |a,b| RangingConfig::<4>::default().with_motion_window(a,b).check();
+
+        assert_eq!(c(400, 1900), Ok(()));
+        assert_eq!(c(399, 1000), Err(ConfigError::MotionWindow(399, 1000)));
+        assert_eq!(c(1000, 2501), Err(ConfigError::MotionWindow(1000, 2501)));
+        assert_eq!(c(2000, 2000), Ok(()));
+        assert_eq!(c(2001, 2000), Err(ConfigError::MotionWindow(2001, 2000)));
+    }
+
+    #[cfg(feature = "detection_thresholds")]
+    #[test]
+    fn thresholds() {
+        use crate::thresholds::{Threshold, ThresholdCheck, ThresholdMeas};
+
+        let (meas, chk) = (ThresholdMeas::DistanceMm, ThresholdCheck::AtMost(500));
+        let th = |row,col| Threshold::new((row,col), meas, chk);
+
+        let c = |ths: ThresholdConfig<4>| RangingConfig::<4>::default().with_thresholds(ths).check();
+        assert_eq!(c(ThresholdConfig::new().with(th(3,3))), Ok(()));
+        assert_eq!(c(ThresholdConfig::new()), Err(ConfigError::NoThresholds));
+        assert_eq!(c(ThresholdConfig::new().with(th(0,4))), Err(ConfigError::ThresholdZone(0,4)));
+
+        let c8 = |ths: ThresholdConfig<8>| RangingConfig::<8>::default().with_thresholds(ths).check();
+        assert_eq!(c8(ThresholdConfig::new().with_all_zones(meas, chk)), Ok(()));
+        assert_eq!(c8(ThresholdConfig::new().with_all_zones(meas, chk).with(th(0,0))), Err(ConfigError::TooManyThresholds(65)));
+    }
+}
@@ -0,0 +1,242 @@
+/*
+* Detection thresholds; vendor plugin.
+*
+* With thresholds programmed, the sensor keeps ranging but only raises 'INT' (and flags data as
+* ready) when the criteria are met, e.g. "something in zone (1,2) closer than 300mm". The MCU
+* can sleep in between.
+*
+* Thresholds are per zone, and several can apply to the same zone (combined with OR, or AND). The
+* sensor takes max. 64 thresholds.
+*
+* Note: Values are given in the units of the measurement ('mm', 'kcps/spad' etc.); the ULD C code
+*       scales them to the sensor's internal format.
+*
+* References:
+*   - vendor's UM2884 > "Detection thresholds"; Rev 5
+*/
+#![cfg(feature = "detection_thresholds")]
+
+use crate::{
+    uld::{with_uld, Uld},
+    uld_raw::{
+        ThresholdMeasurement as Measurement_R,
+        ThresholdType as Type_R,
+        LAST_THRESHOLD,
+        NB_THRESHOLDS,
+        OPERATION_AND,
+        OPERATION_NONE,
+        ST_OK,
+    },
+    ConfigError,
+    Error,
+    Result,
+};
+
+const NB: usize = NB_THRESHOLDS as usize;   // 64
+
+/*
+* What is being checked.
+*/
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ThresholdMeas {
+    DistanceMm,
+    SignalPerSpad,      // kcps/spad
+    RangeSigmaMm,
+    AmbientPerSpad,     // kcps/spad
+    NbTargetDetected,
+    TargetStatus,
+    NbSpadsEnabled,
+    #[cfg(feature = "motion_indicator")]
+    MotionIndicator,
+}
+
+impl ThresholdMeas {
+    fn as_uld(&self) -> Measurement_R {
+        use ThresholdMeas::*;
+        match self {
+            DistanceMm => Measurement_R::DISTANCE_MM,
+            SignalPerSpad => Measurement_R::SIGNAL_PER_SPAD_KCPS,
+            RangeSigmaMm => Measurement_R::RANGE_SIGMA_MM,
+            AmbientPerSpad => Measurement_R::AMBIENT_PER_SPAD_KCPS,
+            NbTargetDetected => Measurement_R::NB_TARGET_DETECTED,
+            TargetStatus => Measurement_R::TARGET_STATUS,
+            NbSpadsEnabled => Measurement_R::NB_SPADS_ENABLED,
+            #[cfg(feature = "motion_indicator")]
+            MotionIndicator => Measurement_R::MOTION_INDICATOR,
+        }
+    }
+}
+
+/*
+* How the value is checked. Window limits are inclusive (vendor docs aren't explicit on this).
+*/
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ThresholdCheck {
+    InWindow(i32,i32),      // low..=high
+    OutOfWindow(i32,i32),
+    AtMost(i32),            // <= v
+    Above(i32),             // > v
+    Equal(i32),
+    NotEqual(i32),
+}
+
+impl ThresholdCheck {
+    // -> (type, low, high)
+    fn as_uld(&self) -> (Type_R, i32, i32) {
+        use ThresholdCheck::*;
+        match *self {
+            InWindow(a,b) => (Type_R::IN_WINDOW, a, b),
+            OutOfWindow(a,b) => (Type_R::OUT_OF_WINDOW, a, b),
+            AtMost(v) => (Type_R::LESS_THAN_EQUAL_MIN, v, 0),
+            Above(v) => (Type_R::GREATER_THAN_MAX, 0, v),
+            Equal(v) => (Type_R::EQUAL_MIN, v, 0),
+            NotEqual(v) => (Type_R::NOT_EQUAL_MIN, v, 0),
+        }
+    }
+}
+
+/*
+* A single criterion, for one zone.
+*
+* Zones are given as '(row, column)', like in the 'ResultsData' matrices.
+*/
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Threshold {
+    zone: (u8,u8),
+    meas: ThresholdMeas,
+    check: ThresholdCheck,
+    and_previous: bool,     // AND with the previous threshold (of the same zone); default is OR
+}
+
+impl Threshold {
+    pub fn new(zone: (u8,u8), meas: ThresholdMeas, check: ThresholdCheck) -> Self {
+        Self{ zone, meas, check, and_previous: false }
+    }
+
+    pub fn distance(zone: (u8,u8), check: ThresholdCheck) -> Self {
+        Self::new(zone, ThresholdMeas::DistanceMm, check)
+    }
+
+    pub fn signal(zone: (u8,u8), check: ThresholdCheck) -> Self {
+        Self::new(zone, ThresholdMeas::SignalPerSpad, check)
+    }
+
+    pub fn status(zone: (u8,u8), check: ThresholdCheck) -> Self {
+        Self::new(zone, ThresholdMeas::TargetStatus, check)
+    }
+
+    /*
+    * Require also the previous threshold (for the same zone) to be met. E.g. "distance < 300mm"
+    * AND "target status == 5".
+    */
+    pub fn and_previous(/*move*/ self) -> Self {
+        Self { and_previous: true, ..self }
+    }
+}
+
+/*
+* A set of thresholds, for a certain resolution.
+*
+* The set is checked (zones within 'DIM', 1..=64 thresholds) by 'RangingConfig::check()', i.e.
+* also when the ranging starts.
+*/
+#[derive(Clone)]
+pub struct ThresholdConfig<const DIM: usize> {
+    ths: [Option<Threshold>;NB],
+    n: usize,       // thresholds given; can be > NB (only the first NB are kept)
+}
+
+impl<const DIM: usize> ThresholdConfig<DIM> {
+    pub fn new() -> Self {
+        Self{ ths: [None;NB], n: 0 }
+    }
+
+    pub fn with(mut self, t: Threshold) -> Self {
+        if self.n < NB {
+            self.ths[self.n] = Some(t);
+        }
+        self.n += 1;
+        self
+    }
+
+    /*
+    * Same criterion for all zones.
+    */
+    pub fn with_all_zones(mut self, meas: ThresholdMeas, check: ThresholdCheck) -> Self {
+        for r in 0..DIM {
+            for c in 0..DIM {
+                self = self.with(Threshold::new((r as u8, c as u8), meas, check));
+            }
+        }
+        self
+    }
+
+    pub(crate) fn check(&self) -> core::result::Result<(), ConfigError> {
+        match self.n {
+            0 => return Err(ConfigError::NoThresholds),
+            n if n > NB => return Err(ConfigError::TooManyThresholds(n)),
+            _ => {}
+        }
+
+        for t in self.ths[..self.n].iter().flatten() {
+            let (r,c) = t.zone;
+            if r as usize >= DIM || c as usize >= DIM {
+                return Err(ConfigError::ThresholdZone(r,c));
+            }
+        }
+        Ok(())
+    }
+
+    // Note: '.check()' is expected to have passed.
+    //
+    pub(crate) fn apply(&self, uld: &mut Uld) -> Result<()> {
+
+        with_uld!(&mut *uld, |vl, raw| {
+            // ULD C API expects a full table; unused entries zeroed. The last used one is marked.
+            let mut tbl: [raw::VL_DetectionThresholds;NB] = [raw::VL_DetectionThresholds{
+                param_low_thresh: 0,
+                param_high_thresh: 0,
+                measurement: 0,
+                type_: 0,
+                zone_num: 0,
+                mathematic_operation: 0,
+            };NB];
+
+            for (i,t) in self.ths[..self.n].iter().enumerate() {
+                let t = t.unwrap();
+                let (type_, low, high) = t.check.as_uld();
+                let zone_num: u8 = t.zone.0 * (DIM as u8) + t.zone.1;
+
+                tbl[i] = raw::VL_DetectionThresholds{
+                    param_low_thresh: low,
+                    param_high_thresh: high,
+                    measurement: t.meas.as_uld() as u8,
+                    type_: type_ as u8,
+                    zone_num: if i == self.n-1 { zone_num | LAST_THRESHOLD } else { zone_num },
+                    mathematic_operation: if t.and_previous { OPERATION_AND } else { OPERATION_NONE },
+                };
+            }
+
+            match unsafe { raw::vl_set_detection_thresholds(vl, tbl.as_mut_ptr()) } {
+                ST_OK => Ok(()),
+                e => Err(vl.error_from(e))
+            }
+        })?;
+
+        Self::enable(uld, true)
+    }
+
+    pub(crate) fn enable(uld: &mut Uld, on: bool) -> Result<()> {
+        with_uld!(uld, |vl, raw| match unsafe { raw::vl_set_detection_thresholds_enable(vl, on as u8) } {
+            ST_OK => Ok(()),
+            e => Err(vl.error_from(e))
+        })
+    }
+}
+
+impl<const DIM: usize> Default for ThresholdConfig<DIM> {
+    fn default() -> Self { Self::new() }
+}
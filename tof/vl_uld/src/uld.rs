@@ -0,0 +1,175 @@
+/*
+* The ULD C driver state, for any of the variants (L5CX, L7CX, L8CX) built in.
+*
+* Normally, there's just one. A "mixed" build (features 'vl53l5cx' and 'vl53l8cx' both enabled)
+* links in both ULD C libraries, with prefixed symbols (see 'Makefile'), and each sensor gets the
+* one its rev id calls for. The driver state ('VL_Configuration'), results struct and functions are
+* separate for each; 'with_uld!' reaches the right ones.
+*/
+use core::ptr;
+
+use crate::{
+    platform::Platform,
+    uld_raw::VL_Platform,
+    Result,
+};
+#[cfg(feature = "vl53l5cx")]
+use crate::uld_raw5;
+#[cfg(feature = "vl53l7cx")]
+use crate::uld_raw7;
+#[cfg(feature = "vl53l8cx")]
+use crate::uld_raw8;
+
+/*
+* The sensor variant, as told by its rev id (only the ones built in).
+*
+* Note: L7CX has the same ids as L5CX (its firmware differs). The ping cannot tell them apart;
+*      a 'vl53l7cx' build takes an L5CX for an L7CX, and vice versa.
+*/
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Variant {
+    #[cfg(feature = "vl53l5cx")]
+    L5CX,
+    #[cfg(feature = "vl53l7cx")]
+    L7CX,
+    #[cfg(feature = "vl53l8cx")]
+    L8CX,
+}
+
+impl Variant {
+    pub(crate) fn from_rev_id(rev_id: u8) -> Option<Self> {
+        match rev_id {
+            #[cfg(feature = "vl53l5cx")]
+            0x02 => Some(Self::L5CX),
+            #[cfg(feature = "vl53l7cx")]
+            0x02 => Some(Self::L7CX),
+            #[cfg(feature = "vl53l8cx")]
+            0x0c => Some(Self::L8CX),
+            _ => None
+        }
+    }
+
+    /*
+    * Field of view (horizontal, vertical) and diagonal, in degrees. From the datasheets.
+    */
+    pub const fn fov_deg(&self) -> (u8,u8) {
+        match self {
+            #[cfg(feature = "vl53l5cx")]
+            Self::L5CX => (45,45),
+            #[cfg(feature = "vl53l7cx")]
+            Self::L7CX => (60,60),
+            #[cfg(feature = "vl53l8cx")]
+            Self::L8CX => (45,45),
+        }
+    }
+
+    pub const fn fov_diagonal_deg(&self) -> u8 {
+        match self {
+            #[cfg(feature = "vl53l5cx")]
+            Self::L5CX => 63,
+            #[cfg(feature = "vl53l7cx")]
+            Self::L7CX => 90,
+            #[cfg(feature = "vl53l8cx")]
+            Self::L8CX => 65,
+        }
+    }
+}
+
+// The one variant of a single sensor build
+#[cfg(all(feature = "vl53l5cx", not(uld_mixed)))]
+pub(crate) const ONLY: Variant = Variant::L5CX;
+#[cfg(feature = "vl53l7cx")]
+pub(crate) const ONLY: Variant = Variant::L7CX;
+#[cfg(all(feature = "vl53l8cx", not(uld_mixed)))]
+pub(crate) const ONLY: Variant = Variant::L8CX;
+
+pub(crate) enum Uld {
+    #[cfg(feature = "vl53l5cx")]
+    L5(uld_raw5::VL_Configuration),
+    #[cfg(feature = "vl53l7cx")]
+    L7(uld_raw7::VL_Configuration),
+    #[cfg(feature = "vl53l8cx")]
+    L8(uld_raw8::VL_Configuration),
+}
+
+/*
+* Run '$body' with '$vl' bound to the variant's 'VL_Configuration' (by reference), and '$raw' to its
+* bindings module. The body is compiled once for each variant built in; it can use 'return' and '?'
+* like any code of the calling function.
+*
+*   <<
+*       with_uld!(&mut self.uld, |vl, raw| match unsafe { raw::vl_stop_ranging(vl) } { ... })
+*   <<
+*/
+macro_rules! with_uld {
+    ($uld:expr, |$vl:ident, $raw:ident| $body:expr) => {
+        match $uld {
+            #[cfg(feature = "vl53l5cx")]
+            crate::uld::Uld::L5($vl) => {
+                #[allow(unused_imports)]
+                use crate::uld_raw5 as $raw;
+                $body
+            },
+            #[cfg(feature = "vl53l7cx")]
+            crate::uld::Uld::L7($vl) => {
+                #[allow(unused_imports)]
+                use crate::uld_raw7 as $raw;
+                $body
+            },
+            #[cfg(feature = "vl53l8cx")]
+            crate::uld::Uld::L8($vl) => {
+                #[allow(unused_imports)]
+                use crate::uld_raw8 as $raw;
+                $body
+            },
+        }
+    }
+}
+pub(crate) use with_uld;
+
+impl Uld {
+    /*
+    * Initialize the ULD C driver of 'variant' (see 'VL_Configuration::init_with()').
+    */
+    pub(crate) fn init_with(variant: Variant, p: impl Platform + 'static) -> Result<Self> {
+        match variant {
+            #[cfg(feature = "vl53l5cx")]
+            Variant::L5CX => uld_raw5::VL_Configuration::init_with(p).map(Self::L5),
+            #[cfg(feature = "vl53l7cx")]
+            Variant::L7CX => uld_raw7::VL_Configuration::init_with(p).map(Self::L7),
+            #[cfg(feature = "vl53l8cx")]
+            Variant::L8CX => uld_raw8::VL_Configuration::init_with(p).map(Self::L8),
+        }
+    }
+
+    pub(crate) fn variant(&self) -> Variant {
+        match self {
+            #[cfg(feature = "vl53l5cx")]
+            Self::L5(_) => Variant::L5CX,
+            #[cfg(feature = "vl53l7cx")]
+            Self::L7(_) => Variant::L7CX,
+            #[cfg(feature = "vl53l8cx")]
+            Self::L8(_) => Variant::L8CX,
+        }
+    }
+
+    /*
+    * The 'Platform' slot. Its layout comes from 'platform.h', and is the same for all variants.
+    */
+    pub(crate) fn platform(&mut self) -> *mut VL_Platform {
+        with_uld!(self, |vl, _raw| ptr::addr_of_mut!(vl.platform).cast())
+    }
+
+    // The buffer the C side reads results (and status) into; for prefetching them (async).
+    //
+    pub(crate) fn temp_buffer(&mut self) -> &mut [u8] {
+        with_uld!(self, |vl, _raw| &mut vl.temp_buffer[..])
+    }
+
+    // Size of the results block; set by 'vl_start_ranging()'.
+    //
+    pub(crate) fn data_read_size(&self) -> u32 {
+        with_uld!(self, |vl, _raw| vl.data_read_size)
+    }
+}
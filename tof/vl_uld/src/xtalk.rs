@@ -0,0 +1,108 @@
+/*
+* Cross-talk ("Xtalk") calibration; vendor plugin.
+*
+* When the sensor is behind a cover glass, part of the emitted light reflects back from the glass
+* itself. Calibration measures this against a known target, and the sensor then compensates for it.
+*
+* Without calibration, 'VL_Configuration::init_with' programs the vendor's default Xtalk data.
+* The calibration result can be read out as an (opaque) 'XtalkData' and stored by the application,
+* e.g. in flash. On later boots, apply it after '.init()', instead of re-calibrating.
+*
+* Calibration is a "factory" step: it takes some seconds, and needs a known target (vendor's example
+* uses a 3% reflective target at 600mm, with 4 samples).
+*
+* References:
+*   - vendor's UM2884 > "Xtalk calibration"; Rev 5
+*/
+#![cfg(feature = "xtalk")]
+
+use crate::{
+    state_hp_idle::State_HP_Idle,
+    uld::with_uld,
+    uld_raw::{
+        XTALK_BUFFER_SIZE,
+        ST_OK,
+    },
+    units::PrcU8,
+    Error,
+    Result,
+};
+
+const N: usize = XTALK_BUFFER_SIZE as usize;     // 776
+
+/*
+* Calibration data; opaque to the application (and us).
+*/
+#[derive(Clone)]
+pub struct XtalkData([u8;N]);
+
+impl XtalkData {
+    pub const SIZE: usize = N;
+
+    pub fn from_bytes(bs: [u8;N]) -> Self {
+        Self(bs)
+    }
+
+    pub fn as_bytes(&self) -> &[u8;N] {
+        &self.0
+    }
+}
+
+impl State_HP_Idle {
+    /*
+    * Calibrate against a known target, and return the results.
+    *
+    * The new calibration is also taken into use.
+    *
+    * Parameter ranges (checked by the ULD C code; 'Error' if outside):
+    *   - reflectance:  1..=99 %
+    *   - nb_samples:   1..=16 (more samples, more accurate, longer calibration)
+    *   - distance_mm:  600..=3000
+    */
+    pub fn calibrate_xtalk(&mut self, reflectance: PrcU8, nb_samples: u8, distance_mm: u16) -> Result<XtalkData> {
+        with_uld!(self.borrow_uld_mut(), |vl, raw| {
+            match unsafe { raw::vl_calibrate_xtalk(vl, reflectance.0 as u16, nb_samples, distance_mm) } {
+                ST_OK => Ok(()),
+                e => Err(vl.error_from(e))
+            }?;
+
+            let mut buf = [0_u8;N];
+            match unsafe { raw::vl_get_caldata_xtalk(vl, buf.as_mut_ptr()) } {
+                ST_OK => Ok(XtalkData(buf)),
+                e => Err(vl.error_from(e))
+            }
+        })
+    }
+
+    /*
+    * Apply a previously stored calibration.
+    *
+    * The data is kept also in the driver; it remains in use over ranging sessions.
+    */
+    pub fn set_xtalk(&mut self, data: &XtalkData) -> Result<()> {
+        let mut buf: [u8;N] = data.0;     // C prototype takes '*mut'; make a copy, to be sure
+
+        with_uld!(self.borrow_uld_mut(), |vl, raw| match unsafe { raw::vl_set_caldata_xtalk(vl, buf.as_mut_ptr()) } {
+            ST_OK => Ok(()),
+            e => Err(vl.error_from(e))
+        })
+    }
+
+    /*
+    * The margin (in kcps/spad) used by the sensor when compensating Xtalk. Vendor default is 50.
+    */
+    pub fn get_xtalk_margin(&mut self) -> Result<u32> {
+        let mut tmp: u32 = 0;
+        with_uld!(self.borrow_uld_mut(), |vl, raw| match unsafe { raw::vl_get_xtalk_margin(vl, &mut tmp) } {
+            ST_OK => Ok(tmp),
+            e => Err(vl.error_from(e))
+        })
+    }
+
+    pub fn set_xtalk_margin(&mut self, v: u32) -> Result<()> {
+        with_uld!(self.borrow_uld_mut(), |vl, raw| match unsafe { raw::vl_set_xtalk_margin(vl, v) } {
+            ST_OK => Ok(()),
+            e => Err(vl.error_from(e))
+        })
+    }
+}
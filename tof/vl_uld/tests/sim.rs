@@ -0,0 +1,112 @@
+/*
+* The ULD C code, run against the simulated sensor ('vl_uld::sim'). No hardware needed.
+*
+*   <<
+*       $ cargo test --target x86_64-unknown-linux-gnu --features=vl53l5cx,sim --test sim
+*   <<
+*
+* Note: 'Meas' is not exported (only reached via 'ResultsData'); we compare its 'Debug' output.
+*/
+use vl_uld::{
+    sim::SimSensor,
+    units::{HzU8, MsU16},
+    ConfigError,
+    Error,
+    I2cAddr,
+    Mode,
+    PlatformError,
+    RangingConfig,
+    StartError,
+    State_HP_Idle,
+    VL53,
+};
+
+fn init(sensor: &SimSensor) -> State_HP_Idle {
+    VL53::new_with_ping(sensor.platform()).expect("ping to succeed")
+        .init().expect("init to succeed")
+}
+
+#[test]
+fn init_range_and_stop() {
+    let sensor = SimSensor::new();
+    sensor.set_frame(1234, 31);
+
+    let vl = init(&sensor);
+    let mut ring = vl.start_ranging(&RangingConfig::<4>::default()).expect("ranging to start");
+    assert!(sensor.is_ranging());
+
+    assert!(ring.is_ready().unwrap());
+    let (res, temp) = ring.get_data().expect("data");
+
+    assert_eq!(temp.0, 31);
+    for row in res.meas[0] {
+        for m in row {
+            assert_eq!(format!("{m:?}"), "Valid(1234)");
+        }
+    }
+
+    let _vl: State_HP_Idle = ring.stop().expect("ranging to stop");
+    assert!(!sensor.is_ranging());
+}
+
+#[test]
+fn frames_follow_the_sensor() {
+    let sensor = SimSensor::new();
+    let mut ring = init(&sensor).start_ranging(&RangingConfig::<8>::default()).unwrap();
+
+    for (mm, t) in [(500, 20), (800, -5)] {
+        sensor.set_frame(mm, t);
+        assert!(ring.is_ready().unwrap());
+
+        let (res, temp) = ring.get_data().unwrap();
+        assert_eq!(temp.0, t);
+        assert_eq!(format!("{:?}", res.meas[0][7][7]), format!("Valid({mm})"));
+    }
+    ring.stop().unwrap();
+}
+
+#[test]
+fn bad_config_keeps_the_handle() {
+    let sensor = SimSensor::new();
+    let bad = RangingConfig::<8>::default().with_mode(Mode::AUTONOMOUS(MsU16(5), HzU8(16)));
+
+    let Err(StartError{ error, vl }) = init(&sensor).start_ranging(&bad) else {
+        panic!("ranging started with a bad config");
+    };
+    assert_eq!(error, Error::Config(ConfigError::Frequency(16)));
+    assert!(!sensor.is_ranging());
+
+    let ring = vl.start_ranging(&RangingConfig::<8>::default()).expect("ranging to start");
+    ring.stop().unwrap();
+}
+
+#[test]
+fn set_i2c_address() {
+    const ADDR: I2cAddr = I2cAddr::from_8bit(0x54);
+
+    let sensor = SimSensor::new();
+    let mut vl = init(&sensor);
+
+    vl.set_i2c_address(&ADDR).expect("address to change");
+    assert!(sensor.i2c_addr() == ADDR);
+
+    // The session continues at the new address...
+    let ring = vl.start_ranging(&RangingConfig::<4>::default()).unwrap();
+    ring.stop().unwrap();
+
+    // ...while the default one no longer answers.
+    match VL53::new_with_ping(sensor.platform()) {
+        Err(Error::Platform(PlatformError::NoAcknowledge)) => {},
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("sensor answered at the default address"),
+    }
+}
+
+#[test]
+fn init_fails_without_firmware() {
+    let sensor = SimSensor::new();
+    sensor.reject_firmware();
+
+    let vl = VL53::new_with_ping(sensor.platform()).unwrap();
+    assert!(matches!(vl.init(), Err(Error::Uld(_))));
+}
@@ -9,6 +9,17 @@
 #include "vl_api.h"
 #include "vl_buffers.h"
 
+// Plugins; 'VL_PLUGIN_...' defines come from 'tmp/config58.h' (Rust features)
+#ifdef VL_PLUGIN_XTALK
+#include "vl_plugin_xtalk.h"
+#endif
+#ifdef VL_PLUGIN_DETECTION_THRESHOLDS
+#include "vl_plugin_detection_thresholds.h"
+#endif
+#ifdef VL_PLUGIN_MOTION_INDICATOR
+#include "vl_plugin_motion_indicator.h"
+#endif
+
 // We don't do standard headers, so... (from '/usr/include/clang/18/include/__stddef_size_t.h'):
 typedef __SIZE_TYPE__ size_t;
 
@@ -56,6 +67,74 @@ enum SyncMode {
 };
 #endif  // L8CX
 
+#ifdef VL_PLUGIN_XTALK
+const uint16_t XTALK_BUFFER_SIZE = VL_XTALK_BUFFER_SIZE;    // 776
+#endif
+
+#ifdef VL_PLUGIN_DETECTION_THRESHOLDS
+const uint8_t NB_THRESHOLDS = VL_NB_THRESHOLDS;      // 64
+const uint8_t LAST_THRESHOLD = VL_LAST_THRESHOLD;    // 128; or'ed to '.zone_num' of the last entry
+
+const uint8_t OPERATION_NONE = VL_OPERATION_NONE;    // 0 (same as 'OR')
+const uint8_t OPERATION_AND = VL_OPERATION_AND;      // 2
+
+enum ThresholdMeasurement {
+    DISTANCE_MM = VL_DISTANCE_MM,                       // 1
+    SIGNAL_PER_SPAD_KCPS = VL_SIGNAL_PER_SPAD_KCPS,     // 2
+    RANGE_SIGMA_MM = VL_RANGE_SIGMA_MM,                 // 4
+    AMBIENT_PER_SPAD_KCPS = VL_AMBIENT_PER_SPAD_KCPS,   // 8
+    NB_TARGET_DETECTED = VL_NB_TARGET_DETECTED,         // 9
+    TARGET_STATUS = VL_TARGET_STATUS,                   // 12
+    NB_SPADS_ENABLED = VL_NB_SPADS_ENABLED,             // 13
+    MOTION_INDICATOR = VL_MOTION_INDICATOR              // 19
+};
+enum ThresholdType {
+    IN_WINDOW = VL_IN_WINDOW,                               // 0
+    OUT_OF_WINDOW = VL_OUT_OF_WINDOW,                       // 1
+    LESS_THAN_EQUAL_MIN = VL_LESS_THAN_EQUAL_MIN_CHECKER,   // 2
+    GREATER_THAN_MAX = VL_GREATER_THAN_MAX_CHECKER,         // 3
+    EQUAL_MIN = VL_EQUAL_MIN_CHECKER,                       // 4
+    NOT_EQUAL_MIN = VL_NOT_EQUAL_MIN_CHECKER                // 5
+};
+#endif
+
+// DCI ("device configuration interface") indices; only used by the 'dci' feature.
+//
+// Note: These are undocumented by the vendor (other than by the ULD C code using them). Each
+//      index has a data size, which the application needs to know; see the vendor C sources.
+//
+const uint16_t DCI_ZONE_CONFIG = VL_DCI_ZONE_CONFIG;        // 0x5450
+const uint16_t DCI_FREQ_HZ = VL_DCI_FREQ_HZ;                // 0x5458
+const uint16_t DCI_INT_TIME = VL_DCI_INT_TIME;              // 0x545c
+const uint16_t DCI_FW_NB_TARGET = VL_DCI_FW_NB_TARGET;      // 0x5478
+const uint16_t DCI_RANGING_MODE = VL_DCI_RANGING_MODE;      // 0xad30
+const uint16_t DCI_DSS_CONFIG = VL_DCI_DSS_CONFIG;          // 0xad38
+const uint16_t DCI_TARGET_ORDER = VL_DCI_TARGET_ORDER;      // 0xae64
+const uint16_t DCI_SHARPENER = VL_DCI_SHARPENER;            // 0xaed8
+const uint16_t DCI_MOTION_DETECTOR_CFG = VL_DCI_MOTION_DETECTOR_CFG;    // 0xbfac
+const uint16_t DCI_SINGLE_RANGE = VL_DCI_SINGLE_RANGE;      // 0xd964
+const uint16_t DCI_OUTPUT_CONFIG = VL_DCI_OUTPUT_CONFIG;    // 0xd968
+const uint16_t DCI_OUTPUT_ENABLES = VL_DCI_OUTPUT_ENABLES;  // 0xd970
+const uint16_t DCI_OUTPUT_LIST = VL_DCI_OUTPUT_LIST;        // 0xd980
+const uint16_t DCI_PIPE_CONTROL = VL_DCI_PIPE_CONTROL;      // 0xdb80
+#ifdef VL_DCI_INTERNAL_CP       // VL53L5CX (not checked for VL53L7CX)
+const uint16_t DCI_INTERNAL_CP = VL_DCI_INTERNAL_CP;        // 0xb39c
+#endif
+#ifdef VL_DCI_SYNC_PIN          // only on VL53L8CX
+const uint16_t DCI_SYNC_PIN = VL_DCI_SYNC_PIN;              // 0xb5f0
+#endif
+#ifdef VL_GLARE_FILTER          // VL53L5CX (not checked for VL53L7CX)
+const uint16_t GLARE_FILTER = VL_GLARE_FILTER;              // 0xe108
+#endif
+
+// Block indices of the results frame; only used by the simulated sensor ('sim' feature), which
+// needs to produce such frames.
+//
+const uint16_t METADATA_IDX = VL_METADATA_IDX;                      // 0x54b4
+const uint16_t NB_TARGET_DETECTED_IDX = VL_NB_TARGET_DETECTED_IDX;
+const uint16_t DISTANCE_IDX = VL_DISTANCE_IDX;
+const uint16_t TARGET_STATUS_IDX = VL_TARGET_STATUS_IDX;
+
 /// @brief Status of operations.
 ///
 ///     Note that official documentation only mentions these cases: